@@ -0,0 +1,11 @@
+use std::process::Command;
+
+// Exposes the compiler version to the about overlay through `option_env!("RUSTC_VERSION")`
+fn main() {
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    if let Ok(output) = Command::new(rustc).arg("--version").output() {
+        let version = String::from_utf8_lossy(&output.stdout);
+        println!("cargo:rustc-env=RUSTC_VERSION={}", version.trim());
+    }
+    println!("cargo:rerun-if-changed=build.rs");
+}
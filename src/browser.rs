@@ -0,0 +1,13 @@
+// Open `url` with the system opener. Without the `browser` feature there is no opener
+// and every call fails.
+#[cfg(feature = "browser")]
+pub fn open(url: &str) -> Result<(), String> {
+    open::that_detached(url).map_err(|e| format!("could not open {url}: {e}"))
+}
+
+#[cfg(not(feature = "browser"))]
+pub fn open(url: &str) -> Result<(), String> {
+    Err(format!(
+        "could not open {url}: built without the browser feature"
+    ))
+}
@@ -0,0 +1,85 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use tokio::sync::mpsc::{
+    self,
+    error::{SendError, TryRecvError, TrySendError},
+    UnboundedReceiver, UnboundedSender,
+};
+
+// The action channel. Unbounded by default, which never loses an action but lets the
+// queue grow without limit if results arrive faster than the loop drains them. With
+// ACTION_CHANNEL_CAPACITY set the channel is bounded instead: a send to a full channel
+// drops the action rather than waiting, so memory stays bounded at the cost of lost
+// results (a dropped load is fixed by the next reload, a dropped RequestFinished by
+// Esc). Dropped actions are counted and shown in the debug overlay.
+pub enum Sender<T> {
+    Unbounded(UnboundedSender<T>),
+    Bounded(mpsc::Sender<T>, Arc<AtomicUsize>),
+}
+
+pub enum Receiver<T> {
+    Unbounded(UnboundedReceiver<T>),
+    Bounded(mpsc::Receiver<T>),
+}
+
+pub fn channel<T>(capacity: Option<usize>) -> (Sender<T>, Receiver<T>) {
+    match capacity {
+        Some(capacity) => {
+            let (tx, rx) = mpsc::channel(capacity);
+            (
+                Sender::Bounded(tx, Arc::new(AtomicUsize::new(0))),
+                Receiver::Bounded(rx),
+            )
+        }
+        None => {
+            let (tx, rx) = mpsc::unbounded_channel();
+            (Sender::Unbounded(tx), Receiver::Unbounded(rx))
+        }
+    }
+}
+
+impl<T> Sender<T> {
+    // Never waits. Only fails once the receiver is gone, a full bounded channel drops
+    // the value and still returns Ok.
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        match self {
+            Sender::Unbounded(tx) => tx.send(value),
+            Sender::Bounded(tx, dropped) => match tx.try_send(value) {
+                Ok(()) => Ok(()),
+                Err(TrySendError::Full(_)) => {
+                    dropped.fetch_add(1, Ordering::Relaxed);
+                    Ok(())
+                }
+                Err(TrySendError::Closed(value)) => Err(SendError(value)),
+            },
+        }
+    }
+
+    pub fn dropped(&self) -> usize {
+        match self {
+            Sender::Unbounded(_) => 0,
+            Sender::Bounded(_, dropped) => dropped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        match self {
+            Sender::Unbounded(tx) => Sender::Unbounded(tx.clone()),
+            Sender::Bounded(tx, dropped) => Sender::Bounded(tx.clone(), dropped.clone()),
+        }
+    }
+}
+
+impl<T> Receiver<T> {
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        match self {
+            Receiver::Unbounded(rx) => rx.try_recv(),
+            Receiver::Bounded(rx) => rx.try_recv(),
+        }
+    }
+}
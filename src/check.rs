@@ -0,0 +1,32 @@
+use crate::{
+    configs::{config, Config},
+    products,
+};
+
+// `--check`: report whether config loads and MongoDB answers a ping, without entering
+// the ui. Returns the process exit code.
+pub async fn run() -> i32 {
+    if let Err(e) = Config::load_from_env() {
+        println!("config: FAIL ({e})");
+        return 1;
+    }
+    println!("config: OK");
+
+    let target = config().redacted_mongo_uri();
+    match products::connect().await {
+        Ok(client) => match products::ping(&client).await {
+            Ok(()) => {
+                println!("mongodb: OK ({target})");
+                0
+            }
+            Err(e) => {
+                println!("mongodb: FAIL ({target}: {e})");
+                1
+            }
+        },
+        Err(e) => {
+            println!("mongodb: FAIL ({target}: {e})");
+            1
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use std::{cell::RefCell, path::PathBuf};
+
+thread_local! {
+    // kept alive for the whole session, on X11 the copied text is gone once it is dropped
+    static CLIPBOARD: RefCell<Option<arboard::Clipboard>> = const { RefCell::new(None) };
+}
+
+// Where copied text goes when no system clipboard is reachable, e.g. over ssh
+fn fallback_path() -> PathBuf {
+    std::env::temp_dir().join("terminal-ui-clipboard.txt")
+}
+
+// Copy `text` to the system clipboard, or write it to a temp file when that fails.
+// Returns where the text ended up, for the status line.
+pub fn copy(text: &str) -> Result<String, String> {
+    let copied = CLIPBOARD.with_borrow_mut(|clipboard| {
+        if clipboard.is_none() {
+            *clipboard = arboard::Clipboard::new().ok();
+        }
+        clipboard
+            .as_mut()
+            .is_some_and(|clipboard| clipboard.set_text(text).is_ok())
+    });
+    if copied {
+        return Ok("clipboard".to_string());
+    }
+
+    let path = fallback_path();
+    std::fs::write(&path, text).map_err(|e| e.to_string())?;
+    Ok(path.display().to_string())
+}
@@ -0,0 +1,143 @@
+use std::env;
+
+use ratatui::{
+    buffer::Buffer,
+    style::{Color, Modifier},
+};
+
+use crate::configs::ColorMode;
+
+// The 16 ANSI colors, in palette order, with the RGB values `rgb` uses for them
+const ANSI: [Color; 16] = [
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::Gray,
+    Color::DarkGray,
+    Color::LightRed,
+    Color::LightGreen,
+    Color::LightYellow,
+    Color::LightBlue,
+    Color::LightMagenta,
+    Color::LightCyan,
+    Color::White,
+];
+
+// Channel values of the 6x6x6 cube in the 256 color palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+// COLOR_MODE=auto: truecolor when COLORTERM says so, 256 colors for *-256color
+// terminals, mono for dumb ones and the 16 ANSI colors otherwise
+pub fn detect() -> ColorMode {
+    let colorterm = env::var("COLORTERM").unwrap_or_default();
+    let term = env::var("TERM").unwrap_or_default();
+    if colorterm == "truecolor" || colorterm == "24bit" {
+        ColorMode::TrueColor
+    } else if term.contains("256color") {
+        ColorMode::Ansi256
+    } else if term == "dumb" {
+        ColorMode::Mono
+    } else {
+        ColorMode::Ansi16
+    }
+}
+
+// Approximate RGB value of a color so it can be scaled or matched to a palette
+pub fn rgb(color: Color) -> (u8, u8, u8) {
+    match color {
+        Color::Rgb(r, g, b) => (r, g, b),
+        Color::Black => (0, 0, 0),
+        Color::Red => (205, 49, 49),
+        Color::Green => (13, 188, 121),
+        Color::Yellow => (229, 229, 16),
+        Color::Blue => (36, 114, 200),
+        Color::Magenta => (188, 63, 188),
+        Color::Cyan => (17, 168, 205),
+        Color::Gray => (204, 204, 204),
+        Color::DarkGray => (118, 118, 118),
+        Color::LightRed => (241, 76, 76),
+        Color::LightGreen => (35, 209, 139),
+        Color::LightYellow => (245, 245, 67),
+        Color::LightBlue => (59, 142, 234),
+        Color::LightMagenta => (214, 112, 214),
+        Color::LightCyan => (41, 184, 219),
+        Color::White => (255, 255, 255),
+        Color::Indexed(i @ 0..=15) => rgb(ANSI[i as usize]),
+        Color::Indexed(i @ 16..=231) => {
+            let i = i - 16;
+            let level = |n: u8| CUBE_LEVELS[n as usize];
+            (level(i / 36), level(i / 6 % 6), level(i % 6))
+        }
+        Color::Indexed(i) => {
+            let gray = 8 + 10 * (i - 232);
+            (gray, gray, gray)
+        }
+        _ => (229, 229, 229),
+    }
+}
+
+// Repaint a finished frame within what `mode` can show. Mono drops colors, reversing
+// highlighted cells and bolding accented text instead.
+pub fn downgrade(buffer: &mut Buffer, mode: ColorMode) {
+    for cell in &mut buffer.content {
+        match mode {
+            ColorMode::Auto | ColorMode::TrueColor => return,
+            ColorMode::Ansi256 => {
+                cell.fg = to_256(cell.fg);
+                cell.bg = to_256(cell.bg);
+            }
+            ColorMode::Ansi16 => {
+                cell.fg = to_16(cell.fg);
+                cell.bg = to_16(cell.bg);
+            }
+            ColorMode::Mono => {
+                if cell.bg != Color::Reset {
+                    cell.modifier.insert(Modifier::REVERSED);
+                } else if !matches!(cell.fg, Color::Reset | Color::White | Color::Gray) {
+                    cell.modifier.insert(Modifier::BOLD);
+                }
+                cell.fg = Color::Reset;
+                cell.bg = Color::Reset;
+            }
+        }
+    }
+}
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+    d(r1, r2) + d(g1, g2) + d(b1, b2)
+}
+
+// Nearest cube or grayscale entry for RGB colors, the rest fit already
+fn to_256(color: Color) -> Color {
+    let Color::Rgb(r, g, b) = color else {
+        return color;
+    };
+    let nearest_level = |channel: u8| {
+        (0..6u8)
+            .min_by_key(|&n| CUBE_LEVELS[n as usize].abs_diff(channel))
+            .unwrap_or_default()
+    };
+    let cube = Color::Indexed(16 + 36 * nearest_level(r) + 6 * nearest_level(g) + nearest_level(b));
+    let average = ((r as u16 + g as u16 + b as u16) / 3) as u8;
+    let gray = Color::Indexed(232 + (average.saturating_sub(8) / 10).min(23));
+    [cube, gray]
+        .into_iter()
+        .min_by_key(|candidate| distance(rgb(*candidate), (r, g, b)))
+        .unwrap_or(cube)
+}
+
+// Nearest ANSI color for RGB and indexed colors, named ones fit already
+fn to_16(color: Color) -> Color {
+    match color {
+        Color::Rgb(..) | Color::Indexed(_) => ANSI
+            .into_iter()
+            .min_by_key(|candidate| distance(rgb(*candidate), rgb(color)))
+            .unwrap_or(color),
+        _ => color,
+    }
+}
@@ -0,0 +1,60 @@
+use crate::{configs::config, Action};
+
+// Commands typed into the ':' palette. The first word may be a COMMAND_ALIASES entry,
+// which is expanded before parsing, so "r5" can stand for "increment 5". Words that
+// are not aliases are parsed as they are.
+pub fn parse(input: &str) -> Result<Action, String> {
+    let input = input.trim();
+    let (word, rest) = input.split_once(' ').unwrap_or((input, ""));
+    let expanded = match config()
+        .COMMAND_ALIASES
+        .iter()
+        .find(|(alias, _)| alias == word)
+    {
+        Some((_, command)) => format!("{command} {rest}"),
+        None => input.to_string(),
+    };
+
+    let mut words = expanded.split_whitespace();
+    let command = words.next().unwrap_or_default();
+    let argument = words.next();
+    if words.next().is_some() {
+        return Err(format!("too many arguments: {expanded}"));
+    }
+    let amount = |sign: i64| -> Result<i64, String> {
+        let amount: i64 = argument
+            .unwrap_or("1")
+            .parse()
+            .map_err(|_| format!("not a number: {}", argument.unwrap_or_default()))?;
+        Ok(sign * amount)
+    };
+
+    let action = match command {
+        "increment" | "inc" => Action::AddToCounter(amount(1)?),
+        "decrement" | "dec" => Action::AddToCounter(amount(-1)?),
+        "animate" => match argument {
+            Some(target) => Action::AnimateTo(
+                target
+                    .parse()
+                    .map_err(|_| format!("not a number: {target}"))?,
+            ),
+            None => return Err("animate needs a target".to_string()),
+        },
+        "load" | "reload" => Action::LoadProducts,
+        "ping" => Action::CheckConnection,
+        "reset" => Action::ClearView,
+        "theme" => Action::CycleTheme,
+        "timezone" => Action::CycleTimezone,
+        "config" => Action::ReloadConfig,
+        "about" => Action::ShowAbout,
+        "debug" => Action::ToggleDebug,
+        "log" => Action::ToggleEventLog,
+        "quit" => Action::AskQuit,
+        "" => return Err("empty command".to_string()),
+        _ => return Err(format!("unknown command: {command}")),
+    };
+    if argument.is_some() && !matches!(action, Action::AddToCounter(_) | Action::AnimateTo(_)) {
+        return Err(format!("{command} takes no argument"));
+    }
+    Ok(action)
+}
@@ -8,7 +8,10 @@ pub enum Error {
 
 impl core::fmt::Display for Error {
     fn fmt(&self, f: &mut core::fmt::Formatter) -> core::result::Result<(), core::fmt::Error> {
-        write!(f, "self:?")
+        match self {
+            Error::MissingEnv(name) => write!(f, "missing env: {name}"),
+            Error::WrongFormat(name) => write!(f, "wrong format: {name}"),
+        }
     }
 }
 
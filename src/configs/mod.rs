@@ -1,8 +1,22 @@
 pub use self::error::{Error, Result};
-use std::{env, str::FromStr, sync::OnceLock};
+use bson::Document;
+use chrono::format::{Item, StrftimeItems};
+use chrono_tz::Tz;
+use ratatui::style::Color;
+use std::{
+    collections::HashSet,
+    env, fs,
+    str::FromStr,
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+use tracing::Level;
 
 mod error;
 
+// How often CONFIG_WAIT_SECS retries reading CONFIG_FILE
+const CONFIG_WAIT_INTERVAL: Duration = Duration::from_millis(250);
+
 pub fn config() -> &'static Config {
     static INSTANCE: OnceLock<Config> = OnceLock::new();
 
@@ -16,13 +30,709 @@ pub fn config() -> &'static Config {
 #[allow(non_snake_case)]
 pub struct Config {
     pub MONGO_URI: String,
+    pub MONGO_DATABASE: String,
+    pub MONGO_COLLECTION: String,
+    pub BANNER_PATH: Option<String>,
+    pub SHOW_BORDERS: bool,
+    pub SELECTION_COLOR: Option<Color>,
+    pub SELECTION_SYMBOL: String,
+    pub MAX_FPS: f64,
+    pub TICK_RATE: f64,
+    pub FRAME_RATE: f64,
+    pub THEME: String,
+    pub WRAP_PRODUCT_NAMES: bool,
+    pub INITIAL_COUNTER: i64,
+    pub DATETIME_FORMAT: String,
+    pub LOG_FILE: Option<String>,
+    pub LOG_FORMAT: LogFormat,
+    pub LOG_LEVEL: Level,
+    pub MAX_PRODUCTS: Option<usize>,
+    pub QUERY_RETRIES: u32,
+    pub BORDER_PULSE: bool,
+    pub BORDER_PULSE_PERIOD_MS: u64,
+    pub ENTER_ACTION: DefaultAction,
+    pub ADAPTIVE_RATE: bool,
+    pub ADAPTIVE_HIGH_DEPTH: usize,
+    pub ADAPTIVE_LOW_DEPTH: usize,
+    pub ADAPTIVE_WINDOW: u32,
+    pub PRODUCT_FILTER: Option<Document>,
+    pub COUNTER_MIN: Option<i64>,
+    pub COUNTER_MAX: Option<i64>,
+    pub COUNTER_WRAP: bool,
+    pub CONFIRM_QUIT: bool,
+    pub CONFIRM_TIMEOUT_SECS: u64,
+    pub SLOW_FRAME_MS: Option<u64>,
+    pub SLOW_FRAME_FLASH: bool,
+    pub LAYOUT: LayoutOrientation,
+    pub SPLIT_PERCENT: Option<u16>,
+    pub DISPLAY_FIELDS: Vec<ProductField>,
+    pub READ_PREFERENCE: ReadMode,
+    pub UNFOCUSED_RATE: Option<f64>,
+    pub EVENT_LOG_LINES: usize,
+    pub EVENT_LOG_PATH: Option<String>,
+    pub EVENT_LOG_MAX_AGE_SECS: Option<u64>,
+    pub BELL_ON: Vec<BellEvent>,
+    pub TIMEZONES: Vec<Tz>,
+    pub ACTION_CHANNEL_CAPACITY: Option<usize>,
+    pub WRAP_TEXT: bool,
+    pub EXIT_DELAY_MS: u64,
+    pub CONNECT_WAIT_SECS: u64,
+    pub EMPTY_IS_ERROR: bool,
+    pub URL_TEMPLATE: Option<String>,
+    pub STALE_AFTER_SECS: Option<u64>,
+    pub KEY_REPEAT: bool,
+    pub KEY_REPEAT_DELAY_MS: u64,
+    pub KEY_REPEAT_INTERVAL_MS: u64,
+    pub KEY_REPEAT_MAX_STEP: i64,
+    pub COUNTER_WARN_DISTANCE: Option<u64>,
+    pub COMMAND_ALIASES: Vec<(String, String)>,
+    pub SCREEN_CAPTURE_PATH: Option<String>,
+    pub EVENT_LOG_LEVEL: LogLevel,
+    pub SORT_FIELD: Option<ProductField>,
+    pub SORT_ORDER: SortOrder,
+    pub ROW_HIGHLIGHT: Option<RowRule>,
+    pub ROW_HIGHLIGHT_COLOR: Color,
+    pub LAYOUT_CACHE: bool,
+    pub ANIMATE_INTERVAL_MS: u64,
+    pub KEYMAP: Vec<(String, String)>,
+    pub KEYMAP_DUPLICATES: KeymapDuplicates,
+    pub KEYMAP_DIR: Option<String>,
+    pub FOCUS_FOLLOWS_MOUSE: bool,
+    pub RENDER_DURING_REQUEST: bool,
+    pub FILTER_DEBOUNCE_MS: u64,
+    pub HIGH_CONTRAST: bool,
+    pub OFFLINE: bool,
+    pub STARTUP_TIMEOUT_SECS: Option<u64>,
+    pub STARTUP_TIMEOUT_ACTION: StartupTimeoutAction,
+    pub MAX_WIDTH: Option<u16>,
+    pub MAX_HEIGHT: Option<u16>,
+    pub PERSIST_COUNTER: bool,
+    pub COUNTER_COLLECTION: String,
+    pub COLOR_MODE: ColorMode,
+    pub NO_TTY: NoTtyAction,
+    pub FPS_BOOST: f64,
+    pub FPS_BOOST_SECS: u64,
+    pub ENFORCE_UNIQUE_CODE: bool,
+    pub PRODUCT_VIEW: ProductView,
+    pub COMPACT: bool,
+    pub FULLSCREEN: bool,
+}
+
+// Action Enter runs on the counter view
+#[derive(Debug, Clone, Copy)]
+pub enum DefaultAction {
+    Reload,
+    Increment,
+    Decrement,
+    NetworkIncrement,
+    NetworkDecrement,
+}
+
+impl FromStr for DefaultAction {
+    type Err = ();
+
+    fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "reload" => Ok(DefaultAction::Reload),
+            "increment" => Ok(DefaultAction::Increment),
+            "decrement" => Ok(DefaultAction::Decrement),
+            "network-increment" => Ok(DefaultAction::NetworkIncrement),
+            "network-decrement" => Ok(DefaultAction::NetworkDecrement),
+            _ => Err(()),
+        }
+    }
+}
+
+// A product field that can be shown in the product views
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProductField {
+    Name,
+    Code,
+    SellerId,
+}
+
+impl ProductField {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ProductField::Name => "name",
+            ProductField::Code => "code",
+            ProductField::SellerId => "seller_id",
+        }
+    }
+}
+
+impl FromStr for ProductField {
+    type Err = ();
+
+    fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "name" => Ok(ProductField::Name),
+            "code" => Ok(ProductField::Code),
+            "seller_id" => Ok(ProductField::SellerId),
+            _ => Err(()),
+        }
+    }
+}
+
+// Which replica set members product reads may go to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadMode {
+    Primary,
+    PrimaryPreferred,
+    Secondary,
+    SecondaryPreferred,
+    Nearest,
+}
+
+impl ReadMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ReadMode::Primary => "primary",
+            ReadMode::PrimaryPreferred => "primaryPreferred",
+            ReadMode::Secondary => "secondary",
+            ReadMode::SecondaryPreferred => "secondaryPreferred",
+            ReadMode::Nearest => "nearest",
+        }
+    }
+}
+
+impl FromStr for ReadMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
+        match s.to_lowercase().replace(['-', '_'], "").as_str() {
+            "primary" => Ok(ReadMode::Primary),
+            "primarypreferred" => Ok(ReadMode::PrimaryPreferred),
+            "secondary" => Ok(ReadMode::Secondary),
+            "secondarypreferred" => Ok(ReadMode::SecondaryPreferred),
+            "nearest" => Ok(ReadMode::Nearest),
+            _ => Err(()),
+        }
+    }
+}
+
+// Events that can ring the terminal bell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BellEvent {
+    Error,
+    Quit,
+    Timer,
+    Request,
+}
+
+impl FromStr for BellEvent {
+    type Err = ();
+
+    fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "error" => Ok(BellEvent::Error),
+            "quit" => Ok(BellEvent::Quit),
+            "timer" => Ok(BellEvent::Timer),
+            "request" => Ok(BellEvent::Request),
+            _ => Err(()),
+        }
+    }
+}
+
+// Severity of an event log line, ordered so a minimum level compares directly
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    pub fn label(&self) -> &'static str {
+        match self {
+            LogLevel::Info => "info",
+            LogLevel::Warn => "warn",
+            LogLevel::Error => "error",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            LogLevel::Info => LogLevel::Warn,
+            LogLevel::Warn => LogLevel::Error,
+            LogLevel::Error => LogLevel::Info,
+        }
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = ();
+
+    fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "info" => Ok(LogLevel::Info),
+            "warn" | "warning" => Ok(LogLevel::Warn),
+            "error" => Ok(LogLevel::Error),
+            _ => Err(()),
+        }
+    }
+}
+
+// A `field operator value` rule picking out products, e.g. "seller_id == 3" or
+// "name contains box"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowRule {
+    pub field: ProductField,
+    pub operator: RuleOperator,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleOperator {
+    Equals,
+    NotEquals,
+    Contains,
+}
+
+impl RowRule {
+    pub fn matches(&self, field_value: &str) -> bool {
+        match self.operator {
+            RuleOperator::Equals => field_value == self.value,
+            RuleOperator::NotEquals => field_value != self.value,
+            RuleOperator::Contains => field_value.contains(&self.value),
+        }
+    }
+}
+
+impl FromStr for RowRule {
+    type Err = ();
+
+    fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
+        let mut parts = s.trim().splitn(3, ' ');
+        let field = parts.next().ok_or(())?.parse()?;
+        let operator = match parts.next().ok_or(())? {
+            "==" => RuleOperator::Equals,
+            "!=" => RuleOperator::NotEquals,
+            "contains" => RuleOperator::Contains,
+            _ => return Err(()),
+        };
+        let value = parts.next().ok_or(())?.trim().to_string();
+        Ok(RowRule {
+            field,
+            operator,
+            value,
+        })
+    }
+}
+
+// What loading KEYMAP does with a key bound more than once
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeymapDuplicates {
+    Error,
+    WarnLast,
+    WarnFirst,
+}
+
+impl FromStr for KeymapDuplicates {
+    type Err = ();
+
+    fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "error" => Ok(KeymapDuplicates::Error),
+            "warn-last" => Ok(KeymapDuplicates::WarnLast),
+            "warn-first" => Ok(KeymapDuplicates::WarnFirst),
+            _ => Err(()),
+        }
+    }
+}
+
+// What happens when startup runs past STARTUP_TIMEOUT_SECS
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartupTimeoutAction {
+    Offline,
+    Exit,
+}
+
+impl FromStr for StartupTimeoutAction {
+    type Err = ();
+
+    fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "offline" => Ok(StartupTimeoutAction::Offline),
+            "exit" => Ok(StartupTimeoutAction::Exit),
+            _ => Err(()),
+        }
+    }
+}
+
+// What to do when started without a terminal to draw on, e.g. piped or in CI
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoTtyAction {
+    Exit,
+    Print,
+}
+
+impl FromStr for NoTtyAction {
+    type Err = ();
+
+    fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "exit" => Ok(NoTtyAction::Exit),
+            "print" => Ok(NoTtyAction::Print),
+            _ => Err(()),
+        }
+    }
+}
+
+// Colors the terminal can show, auto detects them from COLORTERM and TERM
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    Auto,
+    TrueColor,
+    Ansi256,
+    Ansi16,
+    Mono,
+}
+
+impl FromStr for ColorMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(ColorMode::Auto),
+            "truecolor" | "24bit" => Ok(ColorMode::TrueColor),
+            "256" => Ok(ColorMode::Ansi256),
+            "16" => Ok(ColorMode::Ansi16),
+            "mono" => Ok(ColorMode::Mono),
+            _ => Err(()),
+        }
+    }
+}
+
+// Direction SORT_FIELD orders products in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+impl SortOrder {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SortOrder::Ascending => "asc",
+            SortOrder::Descending => "desc",
+        }
+    }
+}
+
+impl FromStr for SortOrder {
+    type Err = ();
+
+    fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "asc" | "ascending" => Ok(SortOrder::Ascending),
+            "desc" | "descending" => Ok(SortOrder::Descending),
+            _ => Err(()),
+        }
+    }
+}
+
+// How the product pane renders its rows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProductView {
+    List,
+    Table,
+}
+
+impl ProductView {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ProductView::List => "list",
+            ProductView::Table => "table",
+        }
+    }
+}
+
+impl FromStr for ProductView {
+    type Err = ();
+
+    fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "list" => Ok(ProductView::List),
+            "table" => Ok(ProductView::Table),
+            _ => Err(()),
+        }
+    }
+}
+
+// How the counter and product panes are arranged
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutOrientation {
+    Vertical,
+    Horizontal,
+}
+
+impl LayoutOrientation {
+    pub fn label(&self) -> &'static str {
+        match self {
+            LayoutOrientation::Vertical => "vertical",
+            LayoutOrientation::Horizontal => "horizontal",
+        }
+    }
+}
+
+impl FromStr for LayoutOrientation {
+    type Err = ();
+
+    fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "vertical" => Ok(LayoutOrientation::Vertical),
+            "horizontal" => Ok(LayoutOrientation::Horizontal),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(()),
+        }
+    }
 }
 
 impl Config {
-    fn load_from_env() -> Result<Config> {
-        Ok(Config {
+    pub fn load_from_env() -> Result<Config> {
+        apply_config_file()?;
+
+        let config = Config {
             MONGO_URI: get_env("MONGO_URI")?,
-        })
+            MONGO_DATABASE: get_env_or("MONGO_DATABASE", "terminal-ui"),
+            MONGO_COLLECTION: get_env_or("MONGO_COLLECTION", "products"),
+            BANNER_PATH: get_env_opt("BANNER_PATH"),
+            SHOW_BORDERS: get_env_parse_or("SHOW_BORDERS", true)?,
+            // an unparsable color is left unset so the accent color is used instead
+            SELECTION_COLOR: get_env_opt("SELECTION_COLOR").and_then(|color| color.parse().ok()),
+            SELECTION_SYMBOL: get_env_or("SELECTION_SYMBOL", "▶ "),
+            MAX_FPS: get_env_parse_or("MAX_FPS", 30.0)?,
+            TICK_RATE: get_env_parse_or("TICK_RATE", 0.1)?,
+            FRAME_RATE: get_env_parse_or("FRAME_RATE", 30.0)?,
+            THEME: get_env_or("THEME", "cyan"),
+            WRAP_PRODUCT_NAMES: get_env_parse_or("WRAP_PRODUCT_NAMES", false)?,
+            INITIAL_COUNTER: get_env_parse_or::<i64>("INITIAL_COUNTER", 0)?,
+            DATETIME_FORMAT: get_env_datetime_format("DATETIME_FORMAT", "%Y-%m-%d %H:%M:%S")?,
+            LOG_FILE: get_env_opt("LOG_FILE"),
+            LOG_FORMAT: get_env_parse_or("LOG_FORMAT", LogFormat::Text)?,
+            LOG_LEVEL: get_env_parse_or("LOG_LEVEL", Level::INFO)?,
+            MAX_PRODUCTS: get_env_parse_opt("MAX_PRODUCTS")?,
+            QUERY_RETRIES: get_env_parse_or("QUERY_RETRIES", 2)?,
+            BORDER_PULSE: get_env_parse_or("BORDER_PULSE", false)?,
+            BORDER_PULSE_PERIOD_MS: get_env_parse_or("BORDER_PULSE_PERIOD_MS", 2000)?,
+            ENTER_ACTION: get_env_parse_or("ENTER_ACTION", DefaultAction::Reload)?,
+            ADAPTIVE_RATE: get_env_parse_or("ADAPTIVE_RATE", false)?,
+            ADAPTIVE_HIGH_DEPTH: get_env_parse_or("ADAPTIVE_HIGH_DEPTH", 64)?,
+            ADAPTIVE_LOW_DEPTH: get_env_parse_or("ADAPTIVE_LOW_DEPTH", 8)?,
+            ADAPTIVE_WINDOW: get_env_parse_or("ADAPTIVE_WINDOW", 10)?,
+            PRODUCT_FILTER: get_env_document("PRODUCT_FILTER")?,
+            COUNTER_MIN: get_env_parse_opt("COUNTER_MIN")?,
+            COUNTER_MAX: get_env_parse_opt("COUNTER_MAX")?,
+            COUNTER_WRAP: get_env_parse_or("COUNTER_WRAP", false)?,
+            CONFIRM_QUIT: get_env_parse_or("CONFIRM_QUIT", false)?,
+            CONFIRM_TIMEOUT_SECS: get_env_parse_or("CONFIRM_TIMEOUT_SECS", 10)?,
+            SLOW_FRAME_MS: get_env_parse_opt("SLOW_FRAME_MS")?,
+            SLOW_FRAME_FLASH: get_env_parse_or("SLOW_FRAME_FLASH", false)?,
+            LAYOUT: get_env_parse_or("LAYOUT", LayoutOrientation::Vertical)?,
+            SPLIT_PERCENT: get_env_parse_opt("SPLIT_PERCENT")?,
+            DISPLAY_FIELDS: get_env_list("DISPLAY_FIELDS", "name,code,seller_id")?,
+            READ_PREFERENCE: get_env_parse_or("READ_PREFERENCE", ReadMode::Primary)?,
+            UNFOCUSED_RATE: get_env_parse_opt("UNFOCUSED_RATE")?,
+            EVENT_LOG_LINES: get_env_parse_or("EVENT_LOG_LINES", 200)?,
+            EVENT_LOG_PATH: get_env_opt("EVENT_LOG_PATH"),
+            EVENT_LOG_MAX_AGE_SECS: get_env_parse_opt("EVENT_LOG_MAX_AGE_SECS")?,
+            BELL_ON: get_env_list("BELL_ON", "")?,
+            TIMEZONES: get_env_list("TIMEZONES", "Asia/Seoul")?,
+            ACTION_CHANNEL_CAPACITY: get_env_parse_opt("ACTION_CHANNEL_CAPACITY")?,
+            WRAP_TEXT: get_env_parse_or("WRAP_TEXT", false)?,
+            EXIT_DELAY_MS: get_env_parse_or("EXIT_DELAY_MS", 0)?,
+            CONNECT_WAIT_SECS: get_env_parse_or("CONNECT_WAIT_SECS", 5)?,
+            EMPTY_IS_ERROR: get_env_parse_or("EMPTY_IS_ERROR", false)?,
+            URL_TEMPLATE: get_env_opt("URL_TEMPLATE"),
+            STALE_AFTER_SECS: get_env_parse_opt("STALE_AFTER_SECS")?,
+            KEY_REPEAT: get_env_parse_or("KEY_REPEAT", false)?,
+            KEY_REPEAT_DELAY_MS: get_env_parse_or("KEY_REPEAT_DELAY_MS", 500)?,
+            KEY_REPEAT_INTERVAL_MS: get_env_parse_or("KEY_REPEAT_INTERVAL_MS", 250)?,
+            KEY_REPEAT_MAX_STEP: get_env_parse_or("KEY_REPEAT_MAX_STEP", 10)?,
+            COUNTER_WARN_DISTANCE: get_env_parse_opt("COUNTER_WARN_DISTANCE")?,
+            COMMAND_ALIASES: get_env_pairs("COMMAND_ALIASES")?,
+            SCREEN_CAPTURE_PATH: get_env_opt("SCREEN_CAPTURE_PATH"),
+            EVENT_LOG_LEVEL: get_env_parse_or("EVENT_LOG_LEVEL", LogLevel::Info)?,
+            SORT_FIELD: get_env_parse_opt("SORT_FIELD")?,
+            SORT_ORDER: get_env_parse_or("SORT_ORDER", SortOrder::Ascending)?,
+            ROW_HIGHLIGHT: get_env_parse_opt("ROW_HIGHLIGHT")?,
+            ROW_HIGHLIGHT_COLOR: get_env_parse_or("ROW_HIGHLIGHT_COLOR", Color::Yellow)?,
+            LAYOUT_CACHE: get_env_parse_or("LAYOUT_CACHE", true)?,
+            ANIMATE_INTERVAL_MS: get_env_parse_or("ANIMATE_INTERVAL_MS", 50)?,
+            KEYMAP: get_env_pairs("KEYMAP")?,
+            KEYMAP_DIR: get_env_opt("KEYMAP_DIR"),
+            FOCUS_FOLLOWS_MOUSE: get_env_parse_or("FOCUS_FOLLOWS_MOUSE", false)?,
+            RENDER_DURING_REQUEST: get_env_parse_or("RENDER_DURING_REQUEST", true)?,
+            FILTER_DEBOUNCE_MS: get_env_parse_or("FILTER_DEBOUNCE_MS", 150)?,
+            HIGH_CONTRAST: get_env_parse_or("HIGH_CONTRAST", false)?,
+            OFFLINE: get_env_parse_or("OFFLINE", false)?,
+            STARTUP_TIMEOUT_SECS: get_env_parse_opt("STARTUP_TIMEOUT_SECS")?,
+            MAX_WIDTH: get_env_parse_opt("MAX_WIDTH")?,
+            MAX_HEIGHT: get_env_parse_opt("MAX_HEIGHT")?,
+            PERSIST_COUNTER: get_env_parse_or("PERSIST_COUNTER", false)?,
+            COUNTER_COLLECTION: get_env_or("COUNTER_COLLECTION", "state"),
+            COLOR_MODE: get_env_parse_or("COLOR_MODE", ColorMode::Auto)?,
+            NO_TTY: get_env_parse_or("NO_TTY", NoTtyAction::Exit)?,
+            FPS_BOOST: get_env_parse_or("FPS_BOOST", 60.0)?,
+            FPS_BOOST_SECS: get_env_parse_or("FPS_BOOST_SECS", 3)?,
+            ENFORCE_UNIQUE_CODE: get_env_parse_or("ENFORCE_UNIQUE_CODE", false)?,
+            PRODUCT_VIEW: get_env_parse_or("PRODUCT_VIEW", ProductView::List)?,
+            COMPACT: get_env_parse_or("COMPACT", false)?,
+            FULLSCREEN: get_env_parse_or("FULLSCREEN", false)?,
+            STARTUP_TIMEOUT_ACTION: get_env_parse_or(
+                "STARTUP_TIMEOUT_ACTION",
+                StartupTimeoutAction::Offline,
+            )?,
+            KEYMAP_DUPLICATES: get_env_parse_or("KEYMAP_DUPLICATES", KeymapDuplicates::WarnLast)?,
+        };
+
+        if let (Some(min), Some(max)) = (config.COUNTER_MIN, config.COUNTER_MAX) {
+            if min > max {
+                return Err(Error::WrongFormat("COUNTER_MAX"));
+            }
+        }
+
+        if config
+            .SPLIT_PERCENT
+            .is_some_and(|percent| !(1..=99).contains(&percent))
+        {
+            return Err(Error::WrongFormat("SPLIT_PERCENT"));
+        }
+
+        // every product query goes to this collection, only OFFLINE runs without one
+        if config.MONGO_COLLECTION.trim().is_empty() && !config.OFFLINE {
+            return Err(Error::WrongFormat("MONGO_COLLECTION"));
+        }
+
+        if config.DISPLAY_FIELDS.is_empty() {
+            return Err(Error::WrongFormat("DISPLAY_FIELDS"));
+        }
+
+        if config.KEY_REPEAT_INTERVAL_MS == 0 {
+            return Err(Error::WrongFormat("KEY_REPEAT_INTERVAL_MS"));
+        }
+
+        if config.KEY_REPEAT_MAX_STEP < 1 {
+            return Err(Error::WrongFormat("KEY_REPEAT_MAX_STEP"));
+        }
+
+        if config.MAX_WIDTH == Some(0) {
+            return Err(Error::WrongFormat("MAX_WIDTH"));
+        }
+
+        if config.MAX_HEIGHT == Some(0) {
+            return Err(Error::WrongFormat("MAX_HEIGHT"));
+        }
+
+        if config.FPS_BOOST <= 0.0 {
+            return Err(Error::WrongFormat("FPS_BOOST"));
+        }
+
+        if config.BORDER_PULSE_PERIOD_MS == 0 {
+            return Err(Error::WrongFormat("BORDER_PULSE_PERIOD_MS"));
+        }
+
+        if config.ANIMATE_INTERVAL_MS == 0 {
+            return Err(Error::WrongFormat("ANIMATE_INTERVAL_MS"));
+        }
+
+        if config.ACTION_CHANNEL_CAPACITY == Some(0) {
+            return Err(Error::WrongFormat("ACTION_CHANNEL_CAPACITY"));
+        }
+
+        if config.TIMEZONES.is_empty() {
+            return Err(Error::WrongFormat("TIMEZONES"));
+        }
+
+        if config.UNFOCUSED_RATE.is_some_and(|rate| rate <= 0.0) {
+            return Err(Error::WrongFormat("UNFOCUSED_RATE"));
+        }
+
+        if config.TICK_RATE <= 0.0 {
+            return Err(Error::WrongFormat("TICK_RATE"));
+        }
+
+        if config.FRAME_RATE <= 0.0 {
+            return Err(Error::WrongFormat("FRAME_RATE"));
+        }
+
+        if config.MAX_FPS <= 0.0 {
+            return Err(Error::WrongFormat("MAX_FPS"));
+        }
+
+        Ok(config)
+    }
+}
+
+// The part of `Config` that ReloadConfig applies to a running app. Everything else is
+// read once at startup and needs a restart to change.
+#[derive(Debug, Clone, PartialEq)]
+#[allow(non_snake_case)]
+pub struct LiveConfig {
+    pub THEME: String,
+    pub TIMEZONES: Vec<Tz>,
+    pub MAX_FPS: f64,
+    pub TICK_RATE: f64,
+    pub FRAME_RATE: f64,
+    pub KEYMAP: Vec<(String, String)>,
+    pub KEYMAP_DUPLICATES: KeymapDuplicates,
+    pub KEYMAP_DIR: Option<String>,
+}
+
+impl LiveConfig {
+    // Re-read and validate the whole environment, keeping only the live settings
+    pub fn load_from_env() -> Result<LiveConfig> {
+        Config::load_from_env().map(|config| LiveConfig::from(&config))
+    }
+}
+
+impl From<&Config> for LiveConfig {
+    fn from(config: &Config) -> Self {
+        LiveConfig {
+            THEME: config.THEME.clone(),
+            TIMEZONES: config.TIMEZONES.clone(),
+            MAX_FPS: config.MAX_FPS,
+            TICK_RATE: config.TICK_RATE,
+            FRAME_RATE: config.FRAME_RATE,
+            KEYMAP: config.KEYMAP.clone(),
+            KEYMAP_DUPLICATES: config.KEYMAP_DUPLICATES,
+            KEYMAP_DIR: config.KEYMAP_DIR.clone(),
+        }
+    }
+}
+
+impl Config {
+    // MONGO_URI with any credentials replaced, safe to show on screen
+    pub fn redacted_mongo_uri(&self) -> String {
+        let uri = &self.MONGO_URI;
+        let Some(scheme_end) = uri.find("://").map(|i| i + 3) else {
+            return "***".to_string();
+        };
+        let authority_end = uri[scheme_end..]
+            .find(['/', '?'])
+            .map_or(uri.len(), |i| scheme_end + i);
+        match uri[scheme_end..authority_end].rfind('@') {
+            Some(at) => format!("{}***{}", &uri[..scheme_end], &uri[scheme_end + at..]),
+            None => uri.clone(),
+        }
     }
 }
 
@@ -30,7 +740,114 @@ fn get_env(name: &'static str) -> Result<String> {
     env::var(name).map_err(|_| Error::MissingEnv(name))
 }
 
+fn get_env_or(name: &'static str, default: &str) -> String {
+    env::var(name).unwrap_or_else(|_| default.to_string())
+}
+
+// chrono panics when formatting with an invalid specifier, so reject those up front
+fn get_env_datetime_format(name: &'static str, default: &str) -> Result<String> {
+    let format = get_env_or(name, default);
+    if StrftimeItems::new(&format).any(|item| item == Item::Error) {
+        return Err(Error::WrongFormat(name));
+    }
+    Ok(format)
+}
+
+// Read `path`, retrying every CONFIG_WAIT_INTERVAL for up to `wait` while it cannot be
+// read, for files that show up shortly after launch such as late container mounts
+fn read_with_wait(path: &str, wait: Duration) -> std::io::Result<String> {
+    let started = Instant::now();
+    loop {
+        match fs::read_to_string(path) {
+            Err(_) if started.elapsed() < wait => std::thread::sleep(CONFIG_WAIT_INTERVAL),
+            result => return result,
+        }
+    }
+}
+
+// With CONFIG_FILE set, every `KEY=VALUE` line of that file (blank lines and `#`
+// comments skipped) is put into the environment before the config is read. Variables
+// that were already set when the process started win over the file, so re-reading it
+// picks up edits to the file without clobbering the real environment.
+fn apply_config_file() -> Result<()> {
+    static STARTUP_ENV: OnceLock<HashSet<String>> = OnceLock::new();
+    let startup = STARTUP_ENV.get().is_none();
+    let startup_env = STARTUP_ENV.get_or_init(|| env::vars().map(|(key, _)| key).collect());
+
+    let Some(path) = get_env_opt("CONFIG_FILE") else {
+        return Ok(());
+    };
+    // only the first load waits, a reload should not stall the ui
+    let wait = if startup {
+        Duration::from_secs(get_env_parse_or("CONFIG_WAIT_SECS", 0)?)
+    } else {
+        Duration::ZERO
+    };
+    let contents = read_with_wait(&path, wait).map_err(|_| Error::WrongFormat("CONFIG_FILE"))?;
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or(Error::WrongFormat("CONFIG_FILE"))?;
+        let key = key.trim();
+        if !startup_env.contains(key) {
+            env::set_var(key, value.trim());
+        }
+    }
+    Ok(())
+}
+
+// Comma separated `key=value` pairs, e.g. "l=load,r5=increment 5"
+fn get_env_pairs(name: &'static str) -> Result<Vec<(String, String)>> {
+    get_env_or(name, "")
+        .split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').ok_or(Error::WrongFormat(name))?;
+            Ok((key.trim().to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+// A comma separated list, every entry parsed as `T`
+fn get_env_list<T: FromStr>(name: &'static str, default: &str) -> Result<Vec<T>> {
+    get_env_or(name, default)
+        .split(',')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(|value| value.parse::<T>().map_err(|_| Error::WrongFormat(name)))
+        .collect()
+}
+
+// A JSON object, parsed into a BSON document
+fn get_env_document(name: &'static str) -> Result<Option<Document>> {
+    get_env_opt(name)
+        .map(|json| serde_json::from_str(&json).map_err(|_| Error::WrongFormat(name)))
+        .transpose()
+}
+
+fn get_env_opt(name: &'static str) -> Option<String> {
+    env::var(name).ok()
+}
+
 fn get_env_parse<T: FromStr>(name: &'static str) -> Result<T> {
     let value = get_env(name)?;
     value.parse::<T>().map_err(|_| Error::WrongFormat(name))
 }
+
+fn get_env_parse_opt<T: FromStr>(name: &'static str) -> Result<Option<T>> {
+    match env::var(name) {
+        Ok(_) => get_env_parse(name).map(Some),
+        Err(_) => Ok(None),
+    }
+}
+
+fn get_env_parse_or<T: FromStr>(name: &'static str, default: T) -> Result<T> {
+    match env::var(name) {
+        Ok(_) => get_env_parse(name),
+        Err(_) => Ok(default),
+    }
+}
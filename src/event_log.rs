@@ -0,0 +1,101 @@
+use std::{
+    collections::VecDeque,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use crate::configs::{config, LogLevel};
+
+// Recent actions shown in the event log panel, oldest first. The log keeps at most
+// EVENT_LOG_LINES lines and, with EVENT_LOG_MAX_AGE_SECS set, nothing older than that,
+// whichever is stricter. Every line starts with the time it was recorded and keeps its
+// level, so the panel can filter while exports stay complete.
+#[derive(Default)]
+pub struct EventLog {
+    lines: VecDeque<Entry>,
+}
+
+struct Entry {
+    recorded: Instant,
+    level: LogLevel,
+    line: String,
+    // where the message starts in `line`, after the timestamp
+    message_start: usize,
+}
+
+impl EventLog {
+    pub fn push(&mut self, level: LogLevel, timestamp: &str, message: &str) {
+        self.lines.push_back(Entry {
+            recorded: Instant::now(),
+            level,
+            line: format!("{timestamp} {message}"),
+            message_start: timestamp.len() + 1,
+        });
+        while self.lines.len() > config().EVENT_LOG_LINES {
+            self.lines.pop_front();
+        }
+        self.prune();
+    }
+
+    // Drop lines past EVENT_LOG_MAX_AGE_SECS, also called on ticks so an idle log ages
+    pub fn prune(&mut self) {
+        let Some(max_age) = config().EVENT_LOG_MAX_AGE_SECS.map(Duration::from_secs) else {
+            return;
+        };
+        while self
+            .lines
+            .front()
+            .is_some_and(|entry| entry.recorded.elapsed() > max_age)
+        {
+            self.lines.pop_front();
+        }
+    }
+
+    // Prune and give spare capacity back, returning how many bytes of capacity that
+    // released. The kept lines themselves are not counted.
+    pub fn compact(&mut self) -> usize {
+        while self.lines.len() > config().EVENT_LOG_LINES {
+            self.lines.pop_front();
+        }
+        self.prune();
+        let capacity = self.lines.capacity();
+        self.lines.shrink_to_fit();
+        (capacity - self.lines.capacity()) * std::mem::size_of::<Entry>()
+    }
+
+    pub fn lines(&self) -> impl DoubleEndedIterator<Item = &String> + ExactSizeIterator {
+        self.lines.iter().map(|entry| &entry.line)
+    }
+
+    // Messages without their timestamp, each with how long ago it was recorded
+    pub fn timeline(&self) -> Vec<(Duration, &str)> {
+        self.lines
+            .iter()
+            .map(|entry| (entry.recorded.elapsed(), &entry.line[entry.message_start..]))
+            .collect()
+    }
+
+    // Lines at `min` or above, errors always included
+    pub fn lines_at(&self, min: LogLevel) -> Vec<&String> {
+        self.lines
+            .iter()
+            .filter(|entry| entry.level >= min || entry.level == LogLevel::Error)
+            .map(|entry| &entry.line)
+            .collect()
+    }
+
+    // Write every line to EVENT_LOG_PATH (a temp file by default), returning the path
+    pub fn export(&self) -> Result<String, String> {
+        let path = config()
+            .EVENT_LOG_PATH
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| std::env::temp_dir().join("terminal-ui-events.log"));
+        let mut contents: String = self.lines().map(|line| format!("{line}\n")).collect();
+        if contents.is_empty() {
+            contents.push('\n');
+        }
+        std::fs::write(&path, contents).map_err(|e| format!("{}: {e}", path.display()))?;
+        Ok(path.display().to_string())
+    }
+}
@@ -0,0 +1,191 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use crossterm::event::KeyCode;
+
+use crate::{
+    command,
+    configs::{KeymapDuplicates, LiveConfig},
+    Action,
+};
+
+// User key bindings, each a key mapped to a command palette command. They are checked
+// before the built-in keys, so a binding can replace one.
+#[derive(Debug, Default, PartialEq)]
+pub struct Keymap {
+    bindings: HashMap<KeyCode, String>,
+}
+
+impl Keymap {
+    // Fails on an unknown key or command, and on a key bound twice when `duplicates`
+    // is "error"; the warn modes log and keep one binding. `source` names where the
+    // pairs came from in messages.
+    fn from_pairs(
+        source: &str,
+        pairs: &[(String, String)],
+        duplicates: KeymapDuplicates,
+    ) -> Result<Self, String> {
+        let mut bindings = HashMap::new();
+        for (key, command) in pairs {
+            let code = parse_key(key).ok_or_else(|| format!("{source}: unknown key {key:?}"))?;
+            command::parse(command).map_err(|e| format!("{source}: {key}: {e}"))?;
+            let Some(previous) = bindings.get(&code) else {
+                bindings.insert(code, command.clone());
+                continue;
+            };
+            match duplicates {
+                KeymapDuplicates::Error => {
+                    return Err(format!("{source}: {key} is bound more than once"));
+                }
+                KeymapDuplicates::WarnLast => {
+                    tracing::warn!(
+                        source,
+                        key,
+                        previous,
+                        command,
+                        "duplicate key binding, keeping the last"
+                    );
+                    bindings.insert(code, command.clone());
+                }
+                KeymapDuplicates::WarnFirst => {
+                    tracing::warn!(
+                        source,
+                        key,
+                        previous,
+                        command,
+                        "duplicate key binding, keeping the first"
+                    );
+                }
+            }
+        }
+        Ok(Self { bindings })
+    }
+
+    pub fn contains(&self, code: KeyCode) -> bool {
+        self.bindings.contains_key(&code)
+    }
+
+    pub fn action(&self, code: KeyCode) -> Action {
+        match self
+            .bindings
+            .get(&code)
+            .map(|command| command::parse(command))
+        {
+            Some(Ok(action)) => action,
+            Some(Err(message)) => Action::Error(message),
+            None => Action::None,
+        }
+    }
+}
+
+// Named keymap profiles: "default" from KEYMAP, then one per file in KEYMAP_DIR named
+// after the file, in name order. Each file holds `key=command` lines, blank lines and
+// `#` comments skipped. All of them are checked when loaded, one is active at a time.
+#[derive(Debug, Default)]
+pub struct Keymaps {
+    profiles: Vec<(String, Keymap)>,
+    active: usize,
+}
+
+impl Keymaps {
+    pub fn load(live: &LiveConfig) -> Result<Self, String> {
+        let mut profiles = vec![(
+            "default".to_string(),
+            Keymap::from_pairs("KEYMAP", &live.KEYMAP, live.KEYMAP_DUPLICATES)?,
+        )];
+        if let Some(dir) = &live.KEYMAP_DIR {
+            let mut files: Vec<_> = fs::read_dir(dir)
+                .map_err(|e| format!("KEYMAP_DIR: {dir}: {e}"))?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.is_file())
+                .collect();
+            files.sort();
+            for path in files {
+                profiles.push(load_file(&path, live.KEYMAP_DUPLICATES)?);
+            }
+        }
+        Ok(Self {
+            profiles,
+            active: 0,
+        })
+    }
+
+    pub fn active(&self) -> &Keymap {
+        &self.profiles[self.active].1
+    }
+
+    pub fn name(&self) -> &str {
+        &self.profiles[self.active].0
+    }
+
+    // Whether both have the same profiles with the same bindings
+    pub fn same_profiles(&self, other: &Keymaps) -> bool {
+        self.profiles == other.profiles
+    }
+
+    // Make the profile called `name` active, if there is one
+    pub fn select(&mut self, name: &str) {
+        if let Some(i) = self
+            .profiles
+            .iter()
+            .position(|(profile, _)| profile == name)
+        {
+            self.active = i;
+        }
+    }
+
+    // Switch to the profile after the active one, wrapping around
+    pub fn cycle(&mut self) {
+        self.active = (self.active + 1) % self.profiles.len();
+    }
+}
+
+fn load_file(path: &Path, duplicates: KeymapDuplicates) -> Result<(String, Keymap), String> {
+    let source = path.display().to_string();
+    let contents = fs::read_to_string(path).map_err(|e| format!("{source}: {e}"))?;
+    let pairs = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let (key, command) = line
+                .split_once('=')
+                .ok_or_else(|| format!("{source}: expected key=command, got {line:?}"))?;
+            Ok((key.trim().to_string(), command.trim().to_string()))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let name = path
+        .file_stem()
+        .map_or(source.clone(), |stem| stem.to_string_lossy().into_owned());
+    Ok((name, Keymap::from_pairs(&source, &pairs, duplicates)?))
+}
+
+// A single character, "f1" to "f12", or a named key such as "enter" or "pageup"
+fn parse_key(spec: &str) -> Option<KeyCode> {
+    let mut chars = spec.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+    let spec = spec.to_lowercase();
+    if let Some(n) = spec.strip_prefix('f').and_then(|n| n.parse().ok()) {
+        return (1..=12).contains(&n).then_some(KeyCode::F(n));
+    }
+    let code = match spec.as_str() {
+        "enter" => KeyCode::Enter,
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "delete" => KeyCode::Delete,
+        "insert" => KeyCode::Insert,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "esc" => KeyCode::Esc,
+        "space" => KeyCode::Char(' '),
+        _ => return None,
+    };
+    Some(code)
+}
@@ -0,0 +1,42 @@
+use std::{collections::HashMap, rc::Rc};
+
+use ratatui::layout::{Layout, Rect};
+
+// Pane rects computed for the current terminal size. A split is keyed by the area and
+// layout it was made from, and everything is dropped when the frame size changes, so
+// steady frames reuse the same rects instead of solving the layout again.
+#[derive(Default)]
+pub struct LayoutCache {
+    size: Rect,
+    splits: HashMap<(Rect, Layout), Rc<[Rect]>>,
+}
+
+impl LayoutCache {
+    pub fn split(&mut self, size: Rect, layout: Layout, area: Rect) -> Rc<[Rect]> {
+        if size != self.size {
+            self.clear();
+            self.size = size;
+        }
+        self.splits
+            .entry((area, layout))
+            .or_insert_with_key(|(area, layout)| layout.split(*area))
+            .clone()
+    }
+
+    pub fn clear(&mut self) {
+        self.splits.clear();
+    }
+
+    // Drop every split and the map's capacity, returning the bytes of capacity released
+    pub fn compact(&mut self) -> usize {
+        let capacity = self.splits.capacity();
+        self.clear();
+        self.splits.shrink_to_fit();
+        let entry = std::mem::size_of::<((Rect, Layout), Rc<[Rect]>)>();
+        (capacity - self.splits.capacity()) * entry
+    }
+
+    pub fn len(&self) -> usize {
+        self.splits.len()
+    }
+}
@@ -0,0 +1,54 @@
+use std::{
+    fs::OpenOptions,
+    sync::{Mutex, OnceLock},
+};
+
+use color_eyre::eyre::{eyre, Result};
+use tracing_subscriber::{
+    filter::LevelFilter, fmt, layer::SubscriberExt, reload, util::SubscriberInitExt, Registry,
+};
+
+use crate::configs::{config, LogFormat};
+
+// Swaps the level filter between LOG_LEVEL and off, set once logging is initialized
+static FILTER: OnceLock<reload::Handle<LevelFilter, Registry>> = OnceLock::new();
+
+// Logs go to LOG_FILE since the terminal itself is owned by the ui, nothing is logged when unset
+pub fn init() -> Result<()> {
+    let Some(path) = &config().LOG_FILE else {
+        return Ok(());
+    };
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+    let (filter, handle) = reload::Layer::new(LevelFilter::from_level(config().LOG_LEVEL));
+    let layer = fmt::layer().with_writer(Mutex::new(file)).with_ansi(false);
+    let registry = tracing_subscriber::registry().with(filter);
+    match config().LOG_FORMAT {
+        LogFormat::Text => registry.with(layer).try_init(),
+        LogFormat::Json => registry.with(layer.json()).try_init(),
+    }
+    .map_err(|e| eyre!(e))?;
+    let _ = FILTER.set(handle);
+    Ok(())
+}
+
+// Whether log lines are written right now, None without LOG_FILE
+pub fn enabled() -> Option<bool> {
+    let handle = FILTER.get()?;
+    handle
+        .with_current(|filter| *filter != LevelFilter::OFF)
+        .ok()
+}
+
+// Pause or resume writing to LOG_FILE, returning whether logging is now on
+pub fn toggle() -> Result<bool, String> {
+    let handle = FILTER.get().ok_or("LOG_FILE is not set")?;
+    let enable = !enabled().unwrap_or(true);
+    let level = if enable {
+        LevelFilter::from_level(config().LOG_LEVEL)
+    } else {
+        LevelFilter::OFF
+    };
+    handle.reload(level).map_err(|e| e.to_string())?;
+    Ok(enable)
+}
@@ -1,27 +1,281 @@
 // ANCHOR: all
+mod browser;
+mod channel;
+mod check;
+mod clipboard;
+mod color;
+mod command;
 mod configs;
+mod event_log;
+mod keymap;
+mod layout_cache;
+mod logging;
+mod overlay;
+mod products;
+mod prompt;
+mod text;
 mod tui;
 
-use std::time::Duration;
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    io::{IsTerminal, Write},
+    ops::ControlFlow,
+    rc::Rc,
+    time::{Duration, Instant},
+};
 
-use chrono::{Timelike, Utc};
-use chrono_tz::Asia::Seoul;
+use chrono::{
+    format::{Item, Numeric, StrftimeItems},
+    Utc,
+};
+use chrono_tz::Tz;
 use color_eyre::eyre::Result;
-use configs::config;
-use crossterm::event::KeyCode::Char;
-use mongodb::{options::ClientOptions, Client};
+use configs::{
+    config, BellEvent, ColorMode, DefaultAction, LayoutOrientation, LiveConfig, LogLevel,
+    NoTtyAction, ProductField, ProductView, StartupTimeoutAction,
+};
+use crossterm::event::{
+    KeyCode::{self, Char},
+    KeyEvent, KeyEventKind, MouseEventKind,
+};
+use event_log::EventLog;
+use keymap::Keymaps;
+use layout_cache::LayoutCache;
+use mongodb::Client;
+use overlay::{Overlay, Overlays};
+use products::Products;
+use prompt::{Prompt, PromptKind};
 use ratatui::{prelude::*, widgets::*};
-use serde::{Deserialize, Serialize};
-use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio_util::sync::CancellationToken;
 use tui::Event;
+use unicode_width::UnicodeWidthStr;
 
 // App state
 struct App {
     counter: i64,
     should_quit: bool,
-    action_tx: UnboundedSender<Action>,
-    client: Client,
+    action_tx: channel::Sender<Action>,
+    // why there is no client when connecting failed at startup, the app is then offline
+    // for the whole session
+    client: std::result::Result<Client, String>,
+    db_connected: bool,
     refresh_datetime: String,
+    refreshed_at: Instant,
+    timestamp_mode: TimestampMode,
+    relative_shown: Option<u64>,
+    timezone: usize,
+    show_seconds: bool,
+    products: Vec<Products>,
+    // everything the last load returned, `products` is the part matching `filter`
+    loaded_products: Vec<Products>,
+    // the last load had more than MAX_PRODUCTS rows and was cut
+    products_truncated: bool,
+    filter: String,
+    // flipped by ReverseList since the last load
+    reversed: bool,
+    // save the counter to COUNTER_COLLECTION on quit
+    persist_counter: bool,
+    // cancels the pending debounced filter while more keys arrive
+    filter_debounce: Option<CancellationToken>,
+    product_state: ListState,
+    last_error: Option<String>,
+    // network action that failed most recently, RetryLast dispatches it again
+    last_failed: Option<Action>,
+    prompt: Option<Prompt>,
+    // when the quit confirmation was opened, if it is showing
+    confirm_quit: Option<Instant>,
+    // label and cancel handle of the critical request blocking input, if any
+    pending_request: Option<(String, CancellationToken)>,
+    // target and cancel handle of the running AnimateTo, if any
+    animation: Option<(i64, CancellationToken)>,
+    // whether the last draw already showed the pending request modal
+    request_frame_drawn: bool,
+    // when the initial product load runs out of STARTUP_TIMEOUT_SECS, until it ends
+    startup_deadline: Option<Instant>,
+    // handles of spawned tasks, finished ones are dropped on ticks
+    tasks: Vec<tokio::task::JoinHandle<()>>,
+    // transient message and when it was set, cleared after STATUS_DURATION
+    status: Option<(String, Instant)>,
+    theme: usize,
+    // settings ReloadConfig can change, read these instead of config()
+    live: LiveConfig,
+    // the border pulse is timed from here
+    pulse_started: Instant,
+    overlays: Overlays,
+    // entries hidden below the bottom of the timeline
+    timeline_scroll: usize,
+    event_log: EventLog,
+    layout_cache: LayoutCache,
+    keymaps: Keymaps,
+    // minimum level the event log panel shows, errors are always shown
+    event_log_level: LogLevel,
+    // while on, key presses are shown instead of dispatched
+    key_inspector: bool,
+    last_key: Option<KeyEvent>,
+    // single line status in place of the counter pane
+    compact: bool,
+    high_contrast: bool,
+    // COLOR_MODE with auto resolved to what the terminal supports
+    color_mode: ColorMode,
+    // labelled counter values, for this session only
+    bookmarks: Vec<(String, i64)>,
+    bookmark_state: ListState,
+    products_fullscreen: bool,
+    focus: Pane,
+    // where each pane was last drawn, for FOCUS_FOLLOWS_MOUSE
+    pane_areas: Vec<(Pane, Rect)>,
+    show_borders: bool,
+    wrap_text: bool,
+    layout: LayoutOrientation,
+    product_view: ProductView,
+    banner: Vec<String>,
+    action_timings: HashMap<&'static str, ActionTiming>,
+    // actions drained in the latest pass of the main loop, and the highest seen
+    queue_depth: usize,
+    peak_queue_depth: usize,
+    rate_slowed: bool,
+    // BoostFrameRate renders at FPS_BOOST until then
+    fps_boost_until: Option<Instant>,
+    // whether the tui is running at the boosted rate right now
+    fps_boosted: bool,
+    // set when ReloadConfig changed the tick or frame rate
+    rates_changed: bool,
+    // automatic ticks are ignored and '.' emits one at a time
+    step_mode: bool,
+    // set by any state change, cleared once a frame is drawn
+    dirty: bool,
+    last_draw: Option<Instant>,
+    hooks: Vec<Hook>,
+    // key held down with KEY_REPEAT and when its repeats started
+    held_key: Option<(KeyCode, Instant)>,
+    // runtime product query narrowing, sent with every load
+    query: products::Query,
+    // when products last loaded successfully, and the stale age last drawn
+    last_loaded: Option<Instant>,
+    stale_shown: Option<u64>,
+    // retry number of a product load in progress, cleared once it succeeds or gives up
+    retry: Option<u32>,
+    // product codes kept at the top of the list, for the whole session
+    pinned: HashSet<String>,
+}
+
+// Called with every action after the built-in update
+type Hook = Box<dyn Fn(&mut App, &Action)>;
+
+impl App {
+    // Hooks run synchronously on the ui thread in registration order, so a slow hook
+    // stalls input and rendering just like a slow built-in action would
+    pub fn register_hook(&mut self, hook: impl Fn(&mut App, &Action) + 'static) {
+        self.hooks.push(Box::new(hook));
+    }
+
+    fn run_hooks(&mut self, action: &Action) {
+        // taken out so each hook can borrow the app mutably, hooks registered meanwhile are kept
+        let mut hooks = std::mem::take(&mut self.hooks);
+        for hook in &hooks {
+            hook(self, action);
+        }
+        hooks.append(&mut self.hooks);
+        self.hooks = hooks;
+    }
+}
+
+// How the last-updated time is shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TimestampMode {
+    Absolute,
+    Relative,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pane {
+    Counter,
+    Products,
+}
+
+// Upper bound for an on-demand ping, so a dead server still gets feedback quickly
+const PING_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Narrower terminals stack the panes even with LAYOUT=horizontal
+const MIN_SIDE_BY_SIDE_WIDTH: u16 = 80;
+
+// Frames of the startup connection spinner, advanced every SPINNER_STEP
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+const SPINNER_STEP: Duration = Duration::from_millis(100);
+
+// With ADAPTIVE_RATE on, the tick and render rates are halved once the drained queue
+// depth has stayed above ADAPTIVE_HIGH_DEPTH for ADAPTIVE_WINDOW loop iterations in a
+// row, and restored after it stays at or below ADAPTIVE_LOW_DEPTH for as long. Timing
+// therefore changes at runtime: ticks and frames arrive half as often while slowed.
+#[derive(Debug, Default)]
+struct AdaptiveRate {
+    backed_up: u32,
+    drained: u32,
+    slowed: bool,
+}
+
+impl AdaptiveRate {
+    // Feed one iteration's queue depth, returns the new rate factor when it changes
+    fn observe(&mut self, depth: usize) -> Option<f64> {
+        if depth > config().ADAPTIVE_HIGH_DEPTH {
+            self.backed_up += 1;
+            self.drained = 0;
+        } else if depth <= config().ADAPTIVE_LOW_DEPTH {
+            self.drained += 1;
+            self.backed_up = 0;
+        }
+
+        if !self.slowed && self.backed_up >= config().ADAPTIVE_WINDOW {
+            self.slowed = true;
+            Some(0.5)
+        } else if self.slowed && self.drained >= config().ADAPTIVE_WINDOW {
+            self.slowed = false;
+            Some(1.0)
+        } else {
+            None
+        }
+    }
+}
+
+// Tick and frame rates the tui should run at. UNFOCUSED_RATE caps an unfocused
+// terminal, the adaptive rate halves both otherwise, and a frame rate boost replaces the
+// frame rate while it lasts.
+fn rates(app: &App, focused: bool) -> (f64, f64) {
+    let (tick_rate, frame_rate) = match config().UNFOCUSED_RATE {
+        Some(rate) if !focused => (app.live.TICK_RATE.min(rate), app.live.FRAME_RATE.min(rate)),
+        _ => {
+            let factor = if app.rate_slowed { 0.5 } else { 1.0 };
+            (app.live.TICK_RATE * factor, app.live.FRAME_RATE * factor)
+        }
+    };
+    if app.fps_boosted {
+        (tick_rate, config().FPS_BOOST)
+    } else {
+        (tick_rate, frame_rate)
+    }
+}
+
+// Number of samples the rolling average is weighted over
+const TIMING_WINDOW: u32 = 32;
+
+// Rolling average of how long `update` takes for one action variant
+#[derive(Debug, Default, Clone, Copy)]
+struct ActionTiming {
+    count: u64,
+    average: Duration,
+}
+
+impl ActionTiming {
+    fn record(&mut self, elapsed: Duration) {
+        self.count += 1;
+        let weight = self.count.min(TIMING_WINDOW as u64) as u32;
+        if elapsed >= self.average {
+            self.average += (elapsed - self.average) / weight;
+        } else {
+            self.average -= (self.average - elapsed) / weight;
+        }
+    }
 }
 
 // App actions
@@ -31,156 +285,3119 @@ pub enum Action {
     Tick,
     Increment,
     Decrement,
+    SetCounter(i64),
+    AddToCounter(i64),
+    HeldCounterKey(KeyCode),
+    KeyReleased,
     NetworkRequestAndThenIncrement, // new
     NetworkRequestAndThenDecrement, // new
+    SimulateDelay(u64),
+    AnimateTo(i64),
+    AnimateStep,
+    CancelRequest,
+    RequestFinished,
+    LoadProducts,
+    Retrying(u32),
+    CheckConnection,
+    ConnectionChecked(Result<Duration, String>),
+    ProductsLoaded(Vec<Products>),
+    SelectNext,
+    SelectPrevious,
+    SelectFirst,
+    SelectLast,
+    ClearView,
+    Error(String),
+    // a network action and why it failed
+    RequestFailed(Box<Action>, String),
+    RetryLast,
+    OpenPrompt(PromptKind),
+    PromptInput(char),
+    PromptBackspace,
+    SubmitPrompt,
+    CancelPrompt,
+    ApplyFilter(String),
+    ProductInserted(String),
+    DuplicateSelected,
+    TogglePin,
+    TogglePersistCounter,
+    ReverseList,
+    OpenProductUrl,
+    CopyVisibleCodes,
+    ExportSettings,
+    Resize,
+    ToggleDebug,
+    ToggleStepMode,
+    BoostFrameRate,
+    ToggleLogging,
+    ToggleEventLog,
+    ToggleTimeline,
+    // positive scrolls towards older entries
+    ScrollTimeline(isize),
+    CycleEventLogLevel,
+    ExportEventLog,
+    CaptureScreen,
+    CompactMemory,
+    ToggleKeyInspector,
+    CycleKeymap,
+    InspectKey(KeyEvent),
+    // close the topmost overlay
+    CloseOverlay,
+    ShowAbout,
+    ShowHelp,
+    HideHelp,
+    ShowConfig,
+    HideConfig,
+    ShowSellerHistogram,
+    HideSellerHistogram,
+    ToggleBookmarks,
+    SelectNextBookmark,
+    SelectPreviousBookmark,
+    ApplyBookmark,
+    HideAbout,
+    ShowProductDetail,
+    HideProductDetail,
+    ToggleBorders,
+    ToggleWrap,
+    ToggleCompact,
+    ToggleHighContrast,
+    ToggleLayout,
+    ToggleView,
+    CycleTheme,
+    CycleTimezone,
+    ReloadConfig,
+    ToggleSeconds,
+    ToggleTimestampMode,
+    ToggleProductsFullscreen,
+    FocusNext,
+    FocusPane(Pane),
+    AskQuit,
+    CancelQuit,
     Quit,
     Render,
     None,
 }
-// ANCHOR_END: action_enum
+// ANCHOR_END: action_enum
+
+impl Action {
+    // Variant name without any payload, used as the timing key
+    fn name(&self) -> &'static str {
+        match self {
+            Action::Tick => "Tick",
+            Action::Increment => "Increment",
+            Action::Decrement => "Decrement",
+            Action::SetCounter(_) => "SetCounter",
+            Action::AddToCounter(_) => "AddToCounter",
+            Action::HeldCounterKey(_) => "HeldCounterKey",
+            Action::KeyReleased => "KeyReleased",
+            Action::NetworkRequestAndThenIncrement => "NetworkRequestAndThenIncrement",
+            Action::NetworkRequestAndThenDecrement => "NetworkRequestAndThenDecrement",
+            Action::SimulateDelay(_) => "SimulateDelay",
+            Action::AnimateTo(_) => "AnimateTo",
+            Action::AnimateStep => "AnimateStep",
+            Action::CancelRequest => "CancelRequest",
+            Action::RequestFinished => "RequestFinished",
+            Action::LoadProducts => "LoadProducts",
+            Action::Retrying(_) => "Retrying",
+            Action::CheckConnection => "CheckConnection",
+            Action::ConnectionChecked(_) => "ConnectionChecked",
+            Action::ProductsLoaded(_) => "ProductsLoaded",
+            Action::SelectNext => "SelectNext",
+            Action::SelectPrevious => "SelectPrevious",
+            Action::SelectFirst => "SelectFirst",
+            Action::SelectLast => "SelectLast",
+            Action::ClearView => "ClearView",
+            Action::Error(_) => "Error",
+            Action::RequestFailed(..) => "RequestFailed",
+            Action::RetryLast => "RetryLast",
+            Action::OpenPrompt(_) => "OpenPrompt",
+            Action::PromptInput(_) => "PromptInput",
+            Action::PromptBackspace => "PromptBackspace",
+            Action::SubmitPrompt => "SubmitPrompt",
+            Action::CancelPrompt => "CancelPrompt",
+            Action::ApplyFilter(_) => "ApplyFilter",
+            Action::ProductInserted(_) => "ProductInserted",
+            Action::DuplicateSelected => "DuplicateSelected",
+            Action::TogglePin => "TogglePin",
+            Action::TogglePersistCounter => "TogglePersistCounter",
+            Action::ReverseList => "ReverseList",
+            Action::OpenProductUrl => "OpenProductUrl",
+            Action::CopyVisibleCodes => "CopyVisibleCodes",
+            Action::ExportSettings => "ExportSettings",
+            Action::Resize => "Resize",
+            Action::ToggleDebug => "ToggleDebug",
+            Action::ToggleStepMode => "ToggleStepMode",
+            Action::BoostFrameRate => "BoostFrameRate",
+            Action::ToggleLogging => "ToggleLogging",
+            Action::ToggleEventLog => "ToggleEventLog",
+            Action::ToggleTimeline => "ToggleTimeline",
+            Action::ScrollTimeline(_) => "ScrollTimeline",
+            Action::CycleEventLogLevel => "CycleEventLogLevel",
+            Action::ExportEventLog => "ExportEventLog",
+            Action::CaptureScreen => "CaptureScreen",
+            Action::CompactMemory => "CompactMemory",
+            Action::ToggleKeyInspector => "ToggleKeyInspector",
+            Action::CycleKeymap => "CycleKeymap",
+            Action::InspectKey(_) => "InspectKey",
+            Action::CloseOverlay => "CloseOverlay",
+            Action::ShowAbout => "ShowAbout",
+            Action::ShowHelp => "ShowHelp",
+            Action::HideHelp => "HideHelp",
+            Action::ShowConfig => "ShowConfig",
+            Action::HideConfig => "HideConfig",
+            Action::ShowSellerHistogram => "ShowSellerHistogram",
+            Action::HideSellerHistogram => "HideSellerHistogram",
+            Action::ToggleBookmarks => "ToggleBookmarks",
+            Action::SelectNextBookmark => "SelectNextBookmark",
+            Action::SelectPreviousBookmark => "SelectPreviousBookmark",
+            Action::ApplyBookmark => "ApplyBookmark",
+            Action::HideAbout => "HideAbout",
+            Action::ShowProductDetail => "ShowProductDetail",
+            Action::HideProductDetail => "HideProductDetail",
+            Action::ToggleBorders => "ToggleBorders",
+            Action::ToggleWrap => "ToggleWrap",
+            Action::ToggleCompact => "ToggleCompact",
+            Action::ToggleHighContrast => "ToggleHighContrast",
+            Action::ToggleLayout => "ToggleLayout",
+            Action::ToggleView => "ToggleView",
+            Action::CycleTheme => "CycleTheme",
+            Action::CycleTimezone => "CycleTimezone",
+            Action::ReloadConfig => "ReloadConfig",
+            Action::ToggleSeconds => "ToggleSeconds",
+            Action::ToggleTimestampMode => "ToggleTimestampMode",
+            Action::ToggleProductsFullscreen => "ToggleProductsFullscreen",
+            Action::FocusNext => "FocusNext",
+            Action::FocusPane(_) => "FocusPane",
+            Action::AskQuit => "AskQuit",
+            Action::CancelQuit => "CancelQuit",
+            Action::Quit => "Quit",
+            Action::Render => "Render",
+            Action::None => "None",
+        }
+    }
+}
+
+// `layout.split(area)`, reused from earlier frames of the same size with LAYOUT_CACHE
+fn split(f: &Frame, app: &mut App, layout: Layout, area: Rect) -> Rc<[Rect]> {
+    if config().LAYOUT_CACHE {
+        app.layout_cache.split(f.size(), layout, area)
+    } else {
+        layout.split(area)
+    }
+}
+
+fn pane_at(app: &App, column: u16, row: u16) -> Option<Pane> {
+    app.pane_areas
+        .iter()
+        .find(|(_, area)| {
+            (area.left()..area.right()).contains(&column)
+                && (area.top()..area.bottom()).contains(&row)
+        })
+        .map(|(pane, _)| *pane)
+}
+
+// The frame, or with MAX_WIDTH / MAX_HEIGHT at most that big and centered in it. Panes
+// and overlays all lay out inside this.
+fn ui_area(f: &Frame) -> Rect {
+    let size = f.size();
+    let width = config()
+        .MAX_WIDTH
+        .map_or(size.width, |max| size.width.min(max));
+    let height = config()
+        .MAX_HEIGHT
+        .map_or(size.height, |max| size.height.min(max));
+    Rect {
+        x: size.x + (size.width - width) / 2,
+        y: size.y + (size.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+// Rect of the given percentage size centered within `area`
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(vertical[1])[1]
+}
+
+fn debug_overlay(f: &mut Frame, app: &App) {
+    let area = centered_rect(60, 60, ui_area(f));
+    let [stats_area, timings_area] = *Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(4), Constraint::Min(0)])
+        .split(area)
+    else {
+        return;
+    };
+
+    let stats = vec![
+        Line::from(format!(
+            "products {} · pinned {} · bookmarks {} · event log {} · cached layouts {}",
+            app.products.len(),
+            app.pinned.len(),
+            app.bookmarks.len(),
+            app.event_log.lines().len(),
+            app.layout_cache.len()
+        )),
+        Line::from(format!(
+            "tasks running {}{}",
+            app.tasks.iter().filter(|task| !task.is_finished()).count(),
+            if app.pending_request.is_some() {
+                " · request pending"
+            } else {
+                ""
+            }
+        )),
+    ];
+
+    let mut timings: Vec<_> = app.action_timings.iter().collect();
+    timings.sort_by_key(|(_, timing)| std::cmp::Reverse(timing.average));
+
+    let rows = timings.into_iter().map(|(name, timing)| {
+        Row::new(vec![
+            name.to_string(),
+            format!("{:?}", timing.average),
+            timing.count.to_string(),
+        ])
+    });
+
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(stats)
+            .block(
+                Block::default()
+                    .title("memory and tasks")
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            )
+            .style(Style::default().fg(Color::Yellow)),
+        stats_area,
+    );
+    f.render_widget(
+        Table::new(
+            rows,
+            [
+                Constraint::Percentage(50),
+                Constraint::Percentage(30),
+                Constraint::Percentage(20),
+            ],
+        )
+        .header(
+            Row::new(vec!["action", "avg", "count"])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .block(
+            Block::default()
+                .title(format!(
+                    "debug, queue depth {} (peak {}), dropped {}, read {}{}",
+                    app.queue_depth,
+                    app.peak_queue_depth,
+                    app.action_tx.dropped(),
+                    config().READ_PREFERENCE.label(),
+                    if app.rate_slowed { ", rate slowed" } else { "" }
+                ))
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded),
+        )
+        .style(Style::default().fg(Color::Yellow)),
+        timings_area,
+    );
+}
+
+// Newest events at the bottom, older ones scroll off the top
+fn event_log_overlay(f: &mut Frame, event_log: &EventLog, level: LogLevel, accent: Color) {
+    let area = centered_rect(70, 60, ui_area(f));
+    let visible = area.height.saturating_sub(2) as usize;
+    let shown = event_log.lines_at(level);
+    let lines: Vec<Line> = shown
+        .iter()
+        .skip(shown.len().saturating_sub(visible))
+        .map(|line| Line::from(line.as_str()))
+        .collect();
+
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .title(format!(
+                        "event log, {} and up (l to close, L level, E to export)",
+                        level.label()
+                    ))
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            )
+            .style(Style::default().fg(accent)),
+        area,
+    );
+}
+
+// Event log entries by age, newest at the bottom unless scrolled back
+fn timeline_overlay(f: &mut Frame, event_log: &EventLog, scroll: usize, accent: Color) {
+    let area = centered_rect(50, 60, ui_area(f));
+    let visible = area.height.saturating_sub(2) as usize;
+    let entries = event_log.timeline();
+    let end = entries.len().saturating_sub(scroll);
+    let lines: Vec<Line> = entries[end.saturating_sub(visible)..end]
+        .iter()
+        .map(|(age, message)| {
+            let secs = age.as_secs();
+            let age = match secs {
+                0..=59 => format!("-{secs}s"),
+                60..=3599 => format!("-{}m", secs / 60),
+                _ => format!("-{}h", secs / 3600),
+            };
+            Line::from(vec![
+                Span::styled(format!("{age:>5} "), Style::default().fg(Color::DarkGray)),
+                Span::raw(format!("● {message}")),
+            ])
+        })
+        .collect();
+
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .title("timeline (↑/↓ scroll, e to close)")
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            )
+            .style(Style::default().fg(accent)),
+        area,
+    );
+}
+
+fn bookmarks_overlay(
+    f: &mut Frame,
+    bookmarks: &[(String, i64)],
+    state: &mut ListState,
+    accent: Color,
+) {
+    let area = centered_rect(40, 40, ui_area(f));
+
+    let items: Vec<ListItem> = if bookmarks.is_empty() {
+        vec![ListItem::new("no bookmarks, press m to add one")]
+    } else {
+        bookmarks
+            .iter()
+            .map(|(label, value)| ListItem::new(format!("{label}: {value}")))
+            .collect()
+    };
+
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(
+        List::new(items)
+            .block(
+                Block::default()
+                    .title("bookmarks (enter to restore, esc to close)")
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            )
+            .style(Style::default().fg(accent))
+            .highlight_style(Style::default().fg(Color::Black).bg(accent)),
+        area,
+        state,
+    );
+}
+
+// Every built-in key, shown by the help overlay. KEYMAP bindings come on top of these.
+const KEYS: [(&str, &str); 52] = [
+    ("j / k", "increment / decrement"),
+    ("J / K", "increment / decrement over the network"),
+    ("+", "add an amount"),
+    ("A", "count to a value"),
+    ("D", "simulate delay"),
+    ("x", "reset the view"),
+    (":", "command"),
+    ("m / M", "add bookmark / bookmarks"),
+    ("↑ / ↓", "select product"),
+    ("home / end", "first / last product"),
+    ("tab", "focus the other pane"),
+    ("enter", "product detail or default action"),
+    ("g", "go to code"),
+    ("/", "filter"),
+    ("S", "seller range"),
+    ("r", "reload products"),
+    ("R", "retry failed request"),
+    ("p", "ping the database"),
+    ("n", "new product"),
+    ("c", "duplicate product"),
+    ("P", "pin product"),
+    ("V", "reverse the list"),
+    ("u", "open product url"),
+    ("y", "copy visible codes"),
+    ("Y", "copy settings"),
+    ("W", "save counter on quit"),
+    ("d", "debug"),
+    ("H / .", "step mode / next tick"),
+    ("F", "boost fps"),
+    ("B", "pause logging"),
+    ("l / L", "event log / log level"),
+    ("E", "export event log"),
+    ("e", "timeline"),
+    ("X", "capture screen"),
+    ("G", "compact memory"),
+    ("a", "about"),
+    ("i", "config"),
+    ("h", "sellers chart"),
+    ("F2 / F3", "key inspector / next keymap"),
+    ("b", "borders"),
+    ("w", "wrap"),
+    ("O", "compact view"),
+    ("I", "high contrast"),
+    ("o", "layout"),
+    ("v", "list or table"),
+    ("f", "fullscreen products"),
+    ("t", "theme"),
+    ("C", "reload config"),
+    ("z", "timezone"),
+    ("s / T", "seconds / relative time"),
+    ("?", "this help"),
+    ("q", "quit"),
+];
+
+// KEYS in as many columns as it takes to fit the height
+fn help_overlay(f: &mut Frame, accent: Color) {
+    let area = centered_rect(90, 80, ui_area(f));
+    let block = Block::default()
+        .title("keys (any key to close)")
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded);
+    let inner = block.inner(area);
+    f.render_widget(Clear, area);
+    f.render_widget(block.style(Style::default().fg(accent)), area);
+
+    let rows = (inner.height as usize).max(1);
+    let columns = KEYS.len().div_ceil(rows);
+    let areas = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(vec![Constraint::Ratio(1, columns as u32); columns])
+        .split(inner);
+    for (keys, area) in KEYS.chunks(rows).zip(areas.iter()) {
+        let lines: Vec<Line> = keys
+            .iter()
+            .map(|(key, description)| {
+                Line::from(vec![
+                    Span::styled(format!("{key:>10} "), Style::default().bold()),
+                    Span::raw(*description),
+                ])
+            })
+            .collect();
+        f.render_widget(
+            Paragraph::new(lines).style(Style::default().fg(accent)),
+            *area,
+        );
+    }
+}
+
+fn about_overlay(f: &mut Frame, accent: Color) {
+    let area = centered_rect(50, 40, ui_area(f));
+
+    let lines = vec![
+        Line::from(format!("terminal-ui {}", env!("CARGO_PKG_VERSION"))),
+        Line::from(option_env!("RUSTC_VERSION").unwrap_or("rustc unknown")),
+        Line::from(format!("mongodb {}", config().redacted_mongo_uri())),
+        Line::from(""),
+        Line::from("press any key to close"),
+    ];
+
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .title("about")
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            )
+            .style(Style::default().fg(accent))
+            .alignment(Alignment::Center),
+        area,
+    );
+}
+
+// Products per seller_id in the loaded list, most products first
+fn seller_histogram_overlay(f: &mut Frame, products: &[Products], accent: Color) {
+    let area = centered_rect(70, 50, ui_area(f));
+    let mut counts: HashMap<i32, u64> = HashMap::new();
+    for product in products {
+        *counts.entry(product.seller_id).or_default() += 1;
+    }
+    let mut counts: Vec<(i32, u64)> = counts.into_iter().collect();
+    counts.sort_by_key(|&(seller_id, count)| (std::cmp::Reverse(count), seller_id));
+
+    let bars: Vec<Bar> = counts
+        .iter()
+        .map(|(seller_id, count)| {
+            Bar::default()
+                .value(*count)
+                .label(Line::from(seller_id.to_string()))
+        })
+        .collect();
+
+    f.render_widget(Clear, area);
+    f.render_widget(
+        BarChart::default()
+            .block(
+                Block::default()
+                    .title(format!(
+                        "products per seller, {} sellers (any key to close)",
+                        counts.len()
+                    ))
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            )
+            .data(BarGroup::default().bars(&bars))
+            .bar_width(5)
+            .bar_gap(1)
+            .style(Style::default().fg(accent)),
+        area,
+    );
+}
+
+// The settings changed in this session as CONFIG_FILE lines, current timezone first.
+// The seller range and filter box have no setting and only go in as a comment.
+fn settings_snippet(app: &App) -> String {
+    let config = config();
+    let mut timezones: Vec<String> = app.live.TIMEZONES.iter().map(Tz::to_string).collect();
+    timezones.rotate_left(app.timezone);
+
+    let mut lines = vec![
+        format!("THEME={}", THEMES[app.theme].0),
+        format!("TIMEZONES={}", timezones.join(",")),
+        format!("MAX_FPS={}", app.live.MAX_FPS),
+        format!("TICK_RATE={}", app.live.TICK_RATE),
+        format!("FRAME_RATE={}", app.live.FRAME_RATE),
+        format!("LAYOUT={}", app.layout.label()),
+        format!("PRODUCT_VIEW={}", app.product_view.label()),
+        format!("COMPACT={}", app.compact),
+        format!("FULLSCREEN={}", app.products_fullscreen),
+        format!("SHOW_BORDERS={}", app.show_borders),
+        format!("WRAP_TEXT={}", app.wrap_text),
+        format!("HIGH_CONTRAST={}", app.high_contrast),
+        format!("PERSIST_COUNTER={}", app.persist_counter),
+        format!("EVENT_LOG_LEVEL={}", app.event_log_level.label()),
+    ];
+    if let Some(filter) = &config.PRODUCT_FILTER {
+        lines.push(format!(
+            "PRODUCT_FILTER={}",
+            serde_json::to_string(filter).unwrap_or_default()
+        ));
+    }
+    if let Some(field) = config.SORT_FIELD {
+        lines.push(format!("SORT_FIELD={}", field.label()));
+        lines.push(format!("SORT_ORDER={}", config.SORT_ORDER.label()));
+    }
+    if let Some((min, max)) = app.query.seller_range {
+        lines.push(format!("# seller range {min}..={max}"));
+    }
+    if !app.filter.is_empty() {
+        lines.push(format!("# filter {}", app.filter));
+    }
+    lines.iter().map(|line| format!("{line}\n")).collect()
+}
+
+// Effective settings after env, CONFIG_FILE and ReloadConfig, credentials redacted
+fn config_overlay(f: &mut Frame, app: &App) {
+    let area = centered_rect(60, 60, ui_area(f));
+    let config = config();
+    let filter = |filter: &Option<bson::Document>| {
+        filter
+            .as_ref()
+            .map_or("none".to_string(), |filter| filter.to_string())
+    };
+    let timezones: Vec<String> = app.live.TIMEZONES.iter().map(Tz::to_string).collect();
+    let seller_range = app
+        .query
+        .seller_range
+        .map_or("none".to_string(), |(min, max)| format!("{min}..={max}"));
+    let sort = config.SORT_FIELD.map_or("none".to_string(), |field| {
+        format!("{} {}", field.label(), config.SORT_ORDER.label())
+    });
+
+    let settings = [
+        ("mongodb", config.redacted_mongo_uri()),
+        ("database", config.MONGO_DATABASE.clone()),
+        ("collection", config.MONGO_COLLECTION.clone()),
+        (
+            "read preference",
+            config.READ_PREFERENCE.label().to_string(),
+        ),
+        ("product filter", filter(&config.PRODUCT_FILTER)),
+        ("seller range", seller_range),
+        ("sort", sort),
+        ("keymap", app.keymaps.name().to_string()),
+        ("theme", THEMES[app.theme].0.to_string()),
+        ("tick rate", format!("{}/s", app.live.TICK_RATE)),
+        ("frame rate", format!("{}/s", app.live.FRAME_RATE)),
+        ("max fps", app.live.MAX_FPS.to_string()),
+        ("timezone", timezone(app).to_string()),
+        ("timezones", timezones.join(", ")),
+        (
+            "config file",
+            std::env::var("CONFIG_FILE").unwrap_or("none".to_string()),
+        ),
+    ];
+    let mut lines: Vec<Line> = settings
+        .into_iter()
+        .map(|(name, value)| {
+            Line::from(vec![
+                Span::styled(format!("{name}: "), Style::default().bold()),
+                Span::raw(value),
+            ])
+        })
+        .collect();
+    lines.push(Line::from(""));
+    lines.push(Line::from("press any key to close"));
+
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .title("configuration")
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            )
+            .style(Style::default().fg(accent(app)))
+            .wrap(Wrap { trim: false }),
+        area,
+    );
+}
+
+// Renders every serialized field, so new `Products` fields show up without changes here
+fn product_detail_overlay(f: &mut Frame, product: &Products, accent: Color) {
+    let area = centered_rect(50, 40, ui_area(f));
+
+    let lines: Vec<Line> = match bson::to_document(product) {
+        Ok(document) => document
+            .iter()
+            .map(|(key, value)| {
+                let value = match value {
+                    bson::Bson::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                Line::from(vec![
+                    Span::styled(
+                        format!("{key}: "),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ),
+                    Span::raw(value),
+                ])
+            })
+            .collect(),
+        Err(e) => vec![Line::from(e.to_string())],
+    };
+
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .title("product (esc to close)")
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            )
+            .style(Style::default().fg(accent)),
+        area,
+    );
+}
+
+fn pending_request_modal(f: &mut Frame, label: &str, accent: Color) {
+    let area = centered_rect(40, 20, ui_area(f));
+
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(vec![
+            Line::from(format!("{label}...")),
+            Line::from(""),
+            Line::from("press esc to cancel"),
+        ])
+        .block(
+            Block::default()
+                .title("please wait")
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded),
+        )
+        .style(Style::default().fg(accent))
+        .alignment(Alignment::Center),
+        area,
+    );
+}
+
+fn connecting_screen(f: &mut Frame, spinner: char, accent: Color) {
+    let area = f.size();
+    let middle = Rect {
+        y: area.y + area.height.saturating_sub(2) / 2,
+        height: area.height.min(2),
+        ..area
+    };
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(vec![
+            Line::from(format!("{spinner} Connecting to database...")),
+            Line::from("press esc to continue offline"),
+        ])
+        .style(Style::default().fg(accent))
+        .alignment(Alignment::Center),
+        middle,
+    );
+}
+
+fn goodbye_screen(f: &mut Frame, accent: Color) {
+    let area = f.size();
+    let middle = Rect {
+        y: area.y + area.height / 2,
+        height: area.height.min(1),
+        ..area
+    };
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new("Goodbye")
+            .style(Style::default().fg(accent))
+            .alignment(Alignment::Center),
+        middle,
+    );
+}
+
+fn key_inspector_overlay(f: &mut Frame, last_key: Option<&KeyEvent>, accent: Color) {
+    let area = centered_rect(50, 30, ui_area(f));
+
+    let lines = match last_key {
+        Some(key) => vec![
+            Line::from(format!("code: {:?}", key.code)),
+            Line::from(format!("modifiers: {:?}", key.modifiers)),
+            Line::from(format!("kind: {:?}", key.kind)),
+        ],
+        None => vec![Line::from("press any key")],
+    };
+
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .title("key inspector (F2 to exit)")
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            )
+            .style(Style::default().fg(accent))
+            .alignment(Alignment::Center),
+        area,
+    );
+}
+
+fn prompt_overlay(f: &mut Frame, prompt: &Prompt, accent: Color) {
+    let area = centered_rect(60, 20, ui_area(f));
+
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(vec![
+            Line::from(format!("{}_", prompt.input)),
+            Line::from(""),
+            Line::from("enter to submit, esc to cancel"),
+        ])
+        .block(
+            Block::default()
+                .title(prompt.kind.label())
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded),
+        )
+        .style(Style::default().fg(accent)),
+        area,
+    );
+}
+
+fn confirm_quit_modal(f: &mut Frame, remaining: Option<Duration>, accent: Color) {
+    let area = centered_rect(40, 20, ui_area(f));
+
+    let mut lines = vec![Line::from("quit? (y/n)")];
+    if let Some(remaining) = remaining {
+        lines.push(Line::from(""));
+        lines.push(Line::from(format!(
+            "cancelling in {}s",
+            remaining.as_secs_f64().ceil()
+        )));
+    }
+
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .title("confirm")
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            )
+            .style(Style::default().fg(accent))
+            .alignment(Alignment::Center),
+        area,
+    );
+}
+
+const TITLE: &str = "ratatui async counter app";
+
+// Accent colors the theme key cycles through, the first one is the default
+const THEMES: [(&str, Color); 6] = [
+    ("cyan", Color::Cyan),
+    ("green", Color::Green),
+    ("magenta", Color::Magenta),
+    ("yellow", Color::Yellow),
+    ("blue", Color::Blue),
+    ("white", Color::White),
+];
+
+const STATUS_DURATION: Duration = Duration::from_secs(3);
+
+fn accent(app: &App) -> Color {
+    THEMES[app.theme].1
+}
+
+// Static accent, or with BORDER_PULSE the accent dimmed and brightened along a sine wave
+// that takes BORDER_PULSE_PERIOD_MS for one full cycle. The phase only moves on ticks,
+// which already redraw, so the pulse adds no frames of its own.
+fn border_color(app: &App) -> Color {
+    if !config().BORDER_PULSE {
+        return accent(app);
+    }
+    let period = config().BORDER_PULSE_PERIOD_MS as f64 / 1000.0;
+    let cycle = app
+        .refreshed_at
+        .saturating_duration_since(app.pulse_started)
+        .as_secs_f64()
+        / period;
+    let angle = cycle.fract() * std::f64::consts::TAU;
+    let brightness = 0.7 + 0.3 * angle.cos();
+    let (r, g, b) = color::rgb(accent(app));
+    let scale = |channel: u8| (channel as f64 * brightness) as u8;
+    Color::Rgb(scale(r), scale(g), scale(b))
+}
+
+fn set_status(app: &mut App, message: impl Into<String>) {
+    app.status = Some((message.into(), Instant::now()));
+}
+
+// The error takes precedence over any transient status message
+fn status_line(app: &App) -> Line<'static> {
+    if let Some(error) = &app.last_error {
+        Line::styled(error.clone(), Style::default().fg(Color::Red))
+    } else if let Some((message, _)) = &app.status {
+        Line::from(message.clone())
+    } else if app.step_mode {
+        Line::from("step mode, . advances one tick, H resumes")
+    } else {
+        Line::from("")
+    }
+}
+
+// Rows of text in the counter paragraph
+const COUNTER_LINES: u16 = 4;
+
+// Rows the counter paragraph needs, either inside the border or below an inline title
+fn counter_height(app: &App) -> u16 {
+    if app.show_borders {
+        COUNTER_LINES + 2
+    } else {
+        COUNTER_LINES + 1
+    }
+}
+
+// The fullscreen product list always has focus
+fn focused(app: &App) -> Pane {
+    if app.products_fullscreen {
+        Pane::Products
+    } else {
+        app.focus
+    }
+}
+
+// Thick border on the focused pane
+fn border_type(app: &App, pane: Pane) -> BorderType {
+    if focused(app) == pane {
+        BorderType::Thick
+    } else {
+        BorderType::Rounded
+    }
+}
+
+// Block for a pane, keeping the title on its own row when borders are off
+fn pane_block(app: &App, title: impl Into<String>, pane: Pane) -> Block<'static> {
+    let title: String = title.into();
+    let block = Block::default().title(title);
+    if app.show_borders {
+        block
+            .borders(Borders::ALL)
+            .border_type(border_type(app, pane))
+            .border_style(Style::default().fg(border_color(app)))
+    } else {
+        block
+    }
+}
+
+// The refresh time as the timestamp mode wants it, remembering a relative age so the
+// render loop knows when it needs to count up
+fn last_updated(app: &mut App) -> String {
+    app.relative_shown = relative_age(app);
+    match app.relative_shown {
+        Some(1) => "1 second ago".to_string(),
+        Some(age) => format!("{age} seconds ago"),
+        None => format!("{:?}", app.refresh_datetime),
+    }
+}
+
+fn relative_age(app: &App) -> Option<u64> {
+    match app.timestamp_mode {
+        TimestampMode::Absolute => None,
+        TimestampMode::Relative => Some(app.refreshed_at.elapsed().as_secs()),
+    }
+}
+
+// With COUNTER_WARN_DISTANCE set, flash a warning once the counter is that close to
+// the i64 range ends, before arithmetic starts saturating
+fn warn_near_limit(app: &mut App) {
+    let Some(distance) = config().COUNTER_WARN_DISTANCE else {
+        return;
+    };
+    let counter = app.counter as i128;
+    let distance = distance as i128;
+    if i64::MAX as i128 - counter <= distance {
+        set_status(app, "warning: counter is close to i64::MAX");
+    } else if counter - i64::MIN as i128 <= distance {
+        set_status(app, "warning: counter is close to i64::MIN");
+    }
+}
+
+// Counter step for a repeat of a held key: 1 until KEY_REPEAT_DELAY_MS into the hold,
+// then one more every KEY_REPEAT_INTERVAL_MS up to KEY_REPEAT_MAX_STEP. A different
+// key starts a new hold.
+fn held_step(app: &mut App, code: KeyCode) -> i64 {
+    let held_since = match app.held_key {
+        Some((held, since)) if held == code => since,
+        _ => {
+            app.held_key = Some((code, Instant::now()));
+            return 1;
+        }
+    };
+    let held = held_since.elapsed().as_millis() as u64;
+    let accelerated = held
+        .checked_sub(config().KEY_REPEAT_DELAY_MS)
+        .map_or(0, |past| past / config().KEY_REPEAT_INTERVAL_MS);
+    (1 + accelerated as i64).min(config().KEY_REPEAT_MAX_STEP)
+}
+
+// Whole seconds since the last successful load, once that passes STALE_AFTER_SECS
+fn stale_age(app: &App) -> Option<u64> {
+    let threshold = config().STALE_AFTER_SECS?;
+    let age = app.last_loaded?.elapsed().as_secs();
+    (age >= threshold).then_some(age)
+}
+
+// Pinned rows are bold and underlined, rows matching ROW_HIGHLIGHT take its color
+fn row_style(app: &App, product: &Products) -> Style {
+    let mut style = Style::default();
+    if app.pinned.contains(&product.code) {
+        style = style.add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+    }
+    if let Some(rule) = &config().ROW_HIGHLIGHT {
+        if rule.matches(&product.field(rule.field)) {
+            style = style.fg(config().ROW_HIGHLIGHT_COLOR);
+        }
+    }
+    style
+}
+
+// A non-name field as it reads in a list row
+fn list_field(product: &Products, field: ProductField) -> String {
+    match field {
+        ProductField::Code => format!("[{}]", product.code),
+        ProductField::SellerId => format!("seller {}", product.seller_id),
+        ProductField::Name => product.field(field),
+    }
+}
+
+fn product_list(f: &mut Frame, app: &mut App, area: Rect) {
+    let mut title = match config().MAX_PRODUCTS {
+        Some(max) if app.products_truncated => format!("products (showing first {max})"),
+        _ => "products".to_string(),
+    };
+    if !app.filter.is_empty() {
+        title.push_str(&format!(" matching {:?}", app.filter));
+    }
+    if app.reversed {
+        title.push_str(" reversed");
+    }
+    if let Some((min, max)) = app.query.seller_range {
+        title.push_str(&format!(" sellers {min}-{max}"));
+    }
+    if let Some(attempt) = app.retry {
+        title.push_str(&format!(" retry {attempt}/{}", config().QUERY_RETRIES));
+    }
+    let mut block = pane_block(app, title, Pane::Products);
+    app.stale_shown = stale_age(app);
+    if let Some(age) = app.stale_shown {
+        block = block.title(
+            block::Title::from(Line::styled(
+                format!(" data is stale ({age}s old) "),
+                Style::default().fg(Color::Yellow),
+            ))
+            .alignment(Alignment::Right),
+        );
+    }
+
+    // dimmed rather than red, an empty result is not an error unless EMPTY_IS_ERROR says so
+    if app.products.is_empty() {
+        let style = if config().EMPTY_IS_ERROR {
+            Style::default().fg(Color::Red)
+        } else {
+            Style::default()
+                .fg(Color::DarkGray)
+                .add_modifier(Modifier::ITALIC)
+        };
+        let inner = block.inner(area);
+        f.render_widget(block.style(Style::default().fg(accent(app))), area);
+        let middle = Rect {
+            y: inner.y + inner.height / 2,
+            height: inner.height.min(1),
+            ..inner
+        };
+        // narrowed by the filter box or a seller range, say so rather than suggest
+        // there is nothing at all
+        let message = match (app.filter.is_empty(), app.query.seller_range) {
+            (false, Some((min, max))) => {
+                format!("no matches for '{}' in sellers {min}-{max}", app.filter)
+            }
+            (false, None) => format!("no matches for '{}'", app.filter),
+            (true, Some((min, max))) => format!("no products in sellers {min}-{max}"),
+            (true, None) => "No products to display".to_string(),
+        };
+        f.render_widget(
+            Paragraph::new(message)
+                .style(style)
+                .alignment(Alignment::Center),
+            middle,
+        );
+        return;
+    }
+
+    if app.product_view == ProductView::Table {
+        product_table(f, app, block, area);
+        return;
+    }
+
+    let symbol_width = config().SELECTION_SYMBOL.width();
+    let row_width = (block.inner(area).width as usize).saturating_sub(symbol_width);
+
+    let items: Vec<ListItem> = app
+        .products
+        .iter()
+        .map(|product| {
+            // fields before the name lead its first line, fields after it trail the last
+            // one, and the name gives way so the other fields always stay visible
+            let fields = &config().DISPLAY_FIELDS;
+            let name_at = fields.iter().position(|f| *f == ProductField::Name);
+            let (before, after) = match name_at {
+                Some(i) => (&fields[..i], &fields[i + 1..]),
+                None => (&fields[..], &[][..]),
+            };
+            let format_fields = |fields: &[ProductField]| {
+                fields
+                    .iter()
+                    .map(|field| list_field(product, *field))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            };
+            let (mut prefix, mut suffix) = (format_fields(before), format_fields(after));
+            if name_at.is_some() && !prefix.is_empty() {
+                prefix.push(' ');
+            }
+            if name_at.is_some() && !suffix.is_empty() {
+                suffix.insert(0, ' ');
+            }
+            let mut lines = match name_at {
+                Some(_) => {
+                    let name_width = row_width.saturating_sub(prefix.width() + suffix.width());
+                    if config().WRAP_PRODUCT_NAMES {
+                        text::wrap(&product.name, name_width)
+                    } else {
+                        vec![text::truncate(&product.name, name_width)]
+                    }
+                }
+                None => vec![String::new()],
+            };
+            if let Some(first) = lines.first_mut() {
+                first.insert_str(0, &prefix);
+            }
+            if let Some(last) = lines.last_mut() {
+                last.push_str(&suffix);
+            }
+            ListItem::new(lines.into_iter().map(Line::from).collect::<Vec<_>>())
+                .style(row_style(app, product))
+        })
+        .collect();
+
+    f.render_stateful_widget(
+        List::new(items)
+            .block(block)
+            .style(Style::default().fg(accent(app)))
+            .highlight_style(
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(config().SELECTION_COLOR.unwrap_or(accent(app))),
+            )
+            .highlight_symbol(&config().SELECTION_SYMBOL),
+        area,
+        &mut app.product_state,
+    );
+}
+
+// Same products as the list, one column per DISPLAY_FIELDS entry. The selection and
+// scroll offset live in app.product_state so they carry over between the two views.
+fn product_table(f: &mut Frame, app: &mut App, block: Block, area: Rect) {
+    let fields = &config().DISPLAY_FIELDS;
+    let fixed_width = |field: ProductField| {
+        let widest = app
+            .products
+            .iter()
+            .map(|product| product.field(field).width())
+            .max()
+            .unwrap_or_default();
+        widest.max(field.label().width())
+    };
+    let widths: Vec<Constraint> = fields
+        .iter()
+        .map(|field| match field {
+            ProductField::Name => Constraint::Min(10),
+            _ => Constraint::Length(fixed_width(*field) as u16),
+        })
+        .collect();
+    // the name column gets what the other columns, the gaps between them and the
+    // selection symbol leave over
+    let name_width = (block.inner(area).width as usize).saturating_sub(
+        fields
+            .iter()
+            .filter(|field| **field != ProductField::Name)
+            .map(|field| fixed_width(*field))
+            .sum::<usize>()
+            + fields.len().saturating_sub(1)
+            + config().SELECTION_SYMBOL.width(),
+    );
+    let header = Row::new(fields.iter().map(|field| field.label()))
+        .style(Style::default().add_modifier(Modifier::BOLD));
+    let rows = app.products.iter().map(|product| {
+        let names = if config().WRAP_PRODUCT_NAMES {
+            text::wrap(&product.name, name_width)
+        } else {
+            vec![text::truncate(&product.name, name_width)]
+        };
+        let height = names.len() as u16;
+        let cells: Vec<Cell> = fields
+            .iter()
+            .map(|field| match field {
+                ProductField::Name => {
+                    let lines: Vec<Line> = names.iter().cloned().map(Line::from).collect();
+                    Cell::from(Text::from(lines))
+                }
+                _ => Cell::from(product.field(*field)),
+            })
+            .collect();
+        Row::new(cells)
+            .height(height)
+            .style(row_style(app, product))
+    });
+
+    let mut state = TableState::default()
+        .with_selected(app.product_state.selected())
+        .with_offset(app.product_state.offset());
+    f.render_stateful_widget(
+        Table::new(rows, widths)
+            .header(header)
+            .block(block)
+            .style(Style::default().fg(accent(app)))
+            .highlight_style(
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(config().SELECTION_COLOR.unwrap_or(accent(app))),
+            )
+            .highlight_symbol(config().SELECTION_SYMBOL.as_str()),
+        area,
+        &mut state,
+    );
+    *app.product_state.offset_mut() = state.offset();
+}
+
+// App ui render function
+fn ui(f: &mut Frame, app: &mut App) {
+    let area = ui_area(f);
+    if app.products_fullscreen {
+        app.pane_areas = vec![(Pane::Products, area)];
+        product_list(f, app, area);
+    } else {
+        main_view(f, app, area);
+    }
+
+    let overlays: Vec<Overlay> = app.overlays.iter().collect();
+    for overlay in overlays {
+        match overlay {
+            Overlay::EventLog => {
+                event_log_overlay(f, &app.event_log, app.event_log_level, accent(app))
+            }
+            Overlay::Timeline => {
+                timeline_overlay(f, &app.event_log, app.timeline_scroll, accent(app))
+            }
+            Overlay::Debug => debug_overlay(f, app),
+            Overlay::ProductDetail => {
+                if let Some(product) = selected_product(app) {
+                    product_detail_overlay(f, product, accent(app));
+                }
+            }
+            Overlay::About => about_overlay(f, accent(app)),
+            Overlay::Help => help_overlay(f, accent(app)),
+            Overlay::Config => config_overlay(f, app),
+            Overlay::SellerHistogram => seller_histogram_overlay(f, &app.products, accent(app)),
+            Overlay::Bookmarks => {
+                let accent = accent(app);
+                bookmarks_overlay(f, &app.bookmarks, &mut app.bookmark_state, accent);
+            }
+        }
+    }
+
+    if app.key_inspector {
+        key_inspector_overlay(f, app.last_key.as_ref(), accent(app));
+    }
+
+    if let Some(prompt) = &app.prompt {
+        prompt_overlay(f, prompt, accent(app));
+    }
+
+    if app.confirm_quit.is_some() {
+        confirm_quit_modal(f, confirm_remaining(app), accent(app));
+    }
+
+    if let Some((label, _)) = &app.pending_request {
+        pending_request_modal(f, label, accent(app));
+    }
+
+    if app.high_contrast {
+        high_contrast(f.buffer_mut());
+    }
+
+    color::downgrade(f.buffer_mut(), app.color_mode);
+}
+
+// Repaint a finished frame white on black in bold, with highlighted cells inverted to
+// black on white and every border drawn thick, so all panes and overlays follow the
+// mode without each picking their own colors
+fn high_contrast(buffer: &mut Buffer) {
+    for cell in &mut buffer.content {
+        let highlighted = cell.bg != Color::Reset;
+        let (fg, bg) = if highlighted {
+            (Color::Black, Color::White)
+        } else {
+            (Color::White, Color::Black)
+        };
+        cell.fg = fg;
+        cell.bg = bg;
+        cell.modifier.insert(Modifier::BOLD);
+        let thick = match cell.symbol() {
+            "╭" | "┌" => "┏",
+            "╮" | "┐" => "┓",
+            "╰" | "└" => "┗",
+            "╯" | "┘" => "┛",
+            "│" => "┃",
+            "─" => "━",
+            _ => continue,
+        };
+        cell.set_symbol(thick);
+    }
+}
+
+// Banner on top, then the counter and product list split along app.layout
+fn main_view(f: &mut Frame, app: &mut App, mut area: Rect) {
+    let banner_height =
+        (app.banner.len() as u16).min(area.height.saturating_sub(counter_height(app)));
+    if banner_height > 0 {
+        let chunks = split(
+            f,
+            app,
+            Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(banner_height), Constraint::Min(0)]),
+            area,
+        );
+        let lines: Vec<Line> = app
+            .banner
+            .iter()
+            .take(banner_height as usize)
+            .map(|line| Line::from(line.as_str()))
+            .collect();
+        f.render_widget(
+            Paragraph::new(lines)
+                .style(Style::default().fg(accent(app)))
+                .alignment(Alignment::Left),
+            chunks[0],
+        );
+        area = chunks[1];
+    }
+
+    if app.compact {
+        return compact_view(f, app, area);
+    }
+
+    let mut lines = Vec::new();
+    if !app.show_borders {
+        lines.push(Line::from(TITLE));
+    }
+    lines.extend([
+        Line::from("j/k counter, ↑/↓ select, r reload, : command, ? all keys"),
+        Line::from(format!("Counter: {}", app.counter)),
+        Line::from(format!(
+            "last updated {} · db {} · counter {} · logging {}",
+            last_updated(app),
+            if app.db_connected {
+                "online"
+            } else {
+                "offline"
+            },
+            match (app.persist_counter, config().PERSIST_COUNTER) {
+                (true, true) => "saved on quit",
+                (true, false) => "saved on quit, read back once PERSIST_COUNTER is set",
+                (false, _) => "session only",
+            },
+            match logging::enabled() {
+                Some(true) => "on",
+                Some(false) => "paused",
+                None => "off",
+            }
+        )),
+        status_line(app),
+    ]);
+
+    // wrapped lines need more rows; word wrapping can take a little more than the
+    // column count suggests, so this is an estimate
+    let mut counter_rows = counter_height(app);
+    if app.wrap_text {
+        let inner_width = if app.show_borders {
+            area.width.saturating_sub(2)
+        } else {
+            area.width
+        }
+        .max(1) as usize;
+        let wrapped: usize = lines
+            .iter()
+            .map(|line| line.width().div_ceil(inner_width).max(1))
+            .sum();
+        counter_rows += (wrapped - lines.len()) as u16;
+    }
+
+    let mut paragraph = Paragraph::new(Text::from(lines))
+        .style(Style::default().fg(accent(app)))
+        .alignment(Alignment::Center);
+    if app.wrap_text {
+        paragraph = paragraph.wrap(Wrap { trim: true });
+    }
+    if app.show_borders {
+        paragraph = paragraph.block(
+            Block::default()
+                .title(TITLE)
+                .title_alignment(Alignment::Center)
+                .borders(Borders::ALL)
+                .border_type(border_type(app, Pane::Counter))
+                .border_style(Style::default().fg(border_color(app))),
+        );
+    }
+    // side by side only when there is room for both panes, otherwise stack them
+    let horizontal =
+        app.layout == LayoutOrientation::Horizontal && area.width >= MIN_SIDE_BY_SIDE_WIDTH;
+    let (direction, counter) = match (horizontal, config().SPLIT_PERCENT) {
+        (true, percent) => (
+            Direction::Horizontal,
+            Constraint::Percentage(percent.unwrap_or(50)),
+        ),
+        (false, Some(percent)) => (Direction::Vertical, Constraint::Percentage(percent)),
+        (false, None) => (Direction::Vertical, Constraint::Length(counter_rows)),
+    };
+    let chunks = split(
+        f,
+        app,
+        Layout::default()
+            .direction(direction)
+            .constraints([counter, Constraint::Min(0)]),
+        area,
+    );
+    f.render_widget(paragraph, chunks[0]);
+    app.pane_areas = vec![(Pane::Counter, chunks[0]), (Pane::Products, chunks[1])];
+
+    product_list(f, app, chunks[1]);
+}
+
+// Counter, timestamp, connection, pending request and status on one line above the
+// product list
+fn compact_view(f: &mut Frame, app: &mut App, area: Rect) {
+    let updated = last_updated(app);
+    let mut spans = vec![Span::raw(format!(
+        "Counter: {} · {updated} · db {}",
+        app.counter,
+        if app.db_connected {
+            "online"
+        } else {
+            "offline"
+        }
+    ))];
+    if let Some((label, _)) = &app.pending_request {
+        spans.push(Span::raw(format!(" · {label}…")));
+    }
+    let status = status_line(app);
+    if status.width() > 0 {
+        spans.push(Span::raw(" · "));
+        spans.extend(status.spans);
+    }
+
+    let chunks = split(
+        f,
+        app,
+        Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(0)]),
+        area,
+    );
+    f.render_widget(
+        Paragraph::new(Line::from(spans)).style(Style::default().fg(accent(app))),
+        chunks[0],
+    );
+    app.pane_areas = vec![(Pane::Counter, chunks[0]), (Pane::Products, chunks[1])];
+
+    product_list(f, app, chunks[1]);
+}
+
+// ANCHOR: get_action
+fn get_action(app: &App, event: Event) -> Action {
+    match event {
+        Event::Error => Action::None,
+        Event::Tick => Action::Tick,
+        Event::Render => Action::Render,
+        // only sent with KEY_REPEAT, and never meant as a key press
+        Event::Key(key) if key.kind == KeyEventKind::Release => Action::KeyReleased,
+        Event::Key(key) if app.pending_request.is_some() => match key.code {
+            KeyCode::Esc => Action::CancelRequest,
+            _ => Action::None,
+        },
+        Event::Key(key) if app.key_inspector => match key.code {
+            KeyCode::F(2) => Action::ToggleKeyInspector,
+            _ => Action::InspectKey(key),
+        },
+        Event::Key(key) if app.confirm_quit.is_some() => match key.code {
+            Char('y') => Action::Quit,
+            Char('n') | KeyCode::Esc => Action::CancelQuit,
+            _ => Action::None,
+        },
+        Event::Key(key) if app.prompt.is_some() => match key.code {
+            KeyCode::Enter => Action::SubmitPrompt,
+            KeyCode::Esc => Action::CancelPrompt,
+            KeyCode::Backspace => Action::PromptBackspace,
+            Char(c) => Action::PromptInput(c),
+            _ => Action::None,
+        },
+        // Esc closes the topmost overlay, which also gets the other keys
+        Event::Key(key) if key.code == KeyCode::Esc && app.overlays.top().is_some() => {
+            Action::CloseOverlay
+        }
+        // the about overlay swallows the next key press to close itself
+        Event::Key(_) if app.overlays.top() == Some(Overlay::About) => Action::HideAbout,
+        Event::Key(_) if app.overlays.top() == Some(Overlay::Help) => Action::HideHelp,
+        Event::Key(_) if app.overlays.top() == Some(Overlay::Config) => Action::HideConfig,
+        Event::Key(_) if app.overlays.top() == Some(Overlay::SellerHistogram) => {
+            Action::HideSellerHistogram
+        }
+        Event::Key(key) if app.overlays.top() == Some(Overlay::Timeline) => match key.code {
+            KeyCode::Up | Char('k') => Action::ScrollTimeline(1),
+            KeyCode::Down | Char('j') => Action::ScrollTimeline(-1),
+            Char('e') => Action::ToggleTimeline,
+            _ => Action::None,
+        },
+        Event::Key(key) if app.overlays.top() == Some(Overlay::Bookmarks) => match key.code {
+            KeyCode::Down => Action::SelectNextBookmark,
+            KeyCode::Up => Action::SelectPreviousBookmark,
+            KeyCode::Enter => Action::ApplyBookmark,
+            Char('M') => Action::ToggleBookmarks,
+            _ => Action::None,
+        },
+        Event::Key(key)
+            if key.kind == KeyEventKind::Repeat && matches!(key.code, Char('j' | 'k')) =>
+        {
+            Action::HeldCounterKey(key.code)
+        }
+        Event::Key(key) if app.keymaps.active().contains(key.code) => {
+            app.keymaps.active().action(key.code)
+        }
+        Event::Key(key) => {
+            match key.code {
+                Char('j') => Action::Increment,
+                Char('k') => Action::Decrement,
+                Char('J') => Action::NetworkRequestAndThenIncrement, // new
+                Char('K') => Action::NetworkRequestAndThenDecrement, // new
+                Char('r') => Action::LoadProducts,
+                Char('R') => Action::RetryLast,
+                Char('p') => Action::CheckConnection,
+                Char('n') => Action::OpenPrompt(PromptKind::NewProduct),
+                Char('+') => Action::OpenPrompt(PromptKind::AddAmount),
+                Char('A') => Action::OpenPrompt(PromptKind::AnimateTo),
+                Char('g') => Action::OpenPrompt(PromptKind::GoToCode),
+                Char('/') => Action::OpenPrompt(PromptKind::Filter),
+                Char('S') => Action::OpenPrompt(PromptKind::SellerRange),
+                Char('D') => Action::OpenPrompt(PromptKind::SimulateDelay),
+                Char(':') => Action::OpenPrompt(PromptKind::Command),
+                Char('m') => Action::OpenPrompt(PromptKind::Bookmark),
+                Char('M') => Action::ToggleBookmarks,
+                Char('c') => Action::DuplicateSelected,
+                Char('P') => Action::TogglePin,
+                Char('V') => Action::ReverseList,
+                Char('W') => Action::TogglePersistCounter,
+                Char('u') => Action::OpenProductUrl,
+                Char('y') => Action::CopyVisibleCodes,
+                Char('Y') => Action::ExportSettings,
+                KeyCode::Down => Action::SelectNext,
+                KeyCode::Up => Action::SelectPrevious,
+                KeyCode::Home => Action::SelectFirst,
+                KeyCode::End => Action::SelectLast,
+                KeyCode::Tab => Action::FocusNext,
+                // Enter opens the selected product in the list, elsewhere it does the main thing
+                KeyCode::Enter => match focused(app) {
+                    Pane::Products => Action::ShowProductDetail,
+                    Pane::Counter => default_action(),
+                },
+                Char('d') => Action::ToggleDebug,
+                Char('H') => Action::ToggleStepMode,
+                Char('F') => Action::BoostFrameRate,
+                Char('B') => Action::ToggleLogging,
+                Char('.') if app.step_mode => Action::Tick,
+                Char('l') => Action::ToggleEventLog,
+                Char('L') => Action::CycleEventLogLevel,
+                Char('E') => Action::ExportEventLog,
+                Char('e') => Action::ToggleTimeline,
+                Char('X') => Action::CaptureScreen,
+                Char('G') => Action::CompactMemory,
+                KeyCode::F(2) => Action::ToggleKeyInspector,
+                KeyCode::F(3) => Action::CycleKeymap,
+                Char('a') => Action::ShowAbout,
+                Char('?') => Action::ShowHelp,
+                Char('i') => Action::ShowConfig,
+                Char('h') => Action::ShowSellerHistogram,
+                Char('b') => Action::ToggleBorders,
+                Char('w') => Action::ToggleWrap,
+                Char('O') => Action::ToggleCompact,
+                Char('I') => Action::ToggleHighContrast,
+                Char('o') => Action::ToggleLayout,
+                Char('v') => Action::ToggleView,
+                Char('x') => Action::ClearView,
+                Char('t') => Action::CycleTheme,
+                Char('z') => Action::CycleTimezone,
+                Char('C') => Action::ReloadConfig,
+                Char('s') => Action::ToggleSeconds,
+                Char('T') => Action::ToggleTimestampMode,
+                Char('f') => Action::ToggleProductsFullscreen,
+                Char('q') => Action::AskQuit,
+                _ => Action::None,
+            }
+        }
+        // the wheel moves the product selection wherever the pointer is
+        Event::Mouse(mouse) => match mouse.kind {
+            MouseEventKind::ScrollDown => Action::SelectNext,
+            MouseEventKind::ScrollUp => Action::SelectPrevious,
+            MouseEventKind::Moved if config().FOCUS_FOLLOWS_MOUSE => {
+                match pane_at(app, mouse.column, mouse.row) {
+                    Some(pane) if pane != app.focus => Action::FocusPane(pane),
+                    _ => Action::None,
+                }
+            }
+            _ => Action::None,
+        },
+        _ => Action::None,
+    }
+}
+// ANCHOR_END: get_action
+
+fn default_action() -> Action {
+    match config().ENTER_ACTION {
+        DefaultAction::Reload => Action::LoadProducts,
+        DefaultAction::Increment => Action::Increment,
+        DefaultAction::Decrement => Action::Decrement,
+        DefaultAction::NetworkIncrement => Action::NetworkRequestAndThenIncrement,
+        DefaultAction::NetworkDecrement => Action::NetworkRequestAndThenDecrement,
+    }
+}
+
+// ANCHOR: update
+fn update(app: &mut App, action: Action) {
+    match action {
+        Action::Tick => {
+            app.refresh_datetime =
+                current_datetime(timezone(app), datetime_format(app).into_iter());
+            app.refreshed_at = Instant::now();
+            app.event_log.prune();
+            app.tasks.retain(|task| !task.is_finished());
+        }
+        Action::Increment => {
+            app.counter = apply_delta(app.counter, 1);
+        }
+        Action::Decrement => {
+            app.counter = apply_delta(app.counter, -1);
+        }
+        Action::SetCounter(value) => app.counter = value,
+        Action::AddToCounter(amount) => app.counter = apply_delta(app.counter, amount),
+        Action::HeldCounterKey(code) => {
+            let step = held_step(app, code);
+            let delta = if code == Char('k') { -step } else { step };
+            app.counter = apply_delta(app.counter, delta);
+        }
+        Action::KeyReleased => app.held_key = None,
+        // same modal flow as the network actions, with a chosen duration for testing
+        Action::SimulateDelay(ms) => {
+            spawn_critical(app, &format!("simulating {ms} ms delay"), async move {
+                tokio::time::sleep(Duration::from_millis(ms)).await;
+                Action::Increment
+            })
+        }
+        Action::NetworkRequestAndThenIncrement => spawn_critical(app, "incrementing", async {
+            tokio::time::sleep(Duration::from_secs(5)).await; // simulate network request
+            Action::Increment
+        }),
+        Action::NetworkRequestAndThenDecrement => spawn_critical(app, "decrementing", async {
+            tokio::time::sleep(Duration::from_secs(5)).await; // simulate network request
+            Action::Decrement
+        }),
+        Action::AnimateTo(target) => {
+            let (min, max) = (config().COUNTER_MIN, config().COUNTER_MAX);
+            if min.is_some_and(|min| target < min) || max.is_some_and(|max| target > max) {
+                return update(
+                    app,
+                    Action::Error(format!("{target} is outside the counter range")),
+                );
+            }
+            if let Some((_, token)) = app.animation.take() {
+                token.cancel();
+            }
+            let token = CancellationToken::new();
+            app.animation = Some((target, token.clone()));
+            let tx = app.action_tx.clone();
+            let interval = Duration::from_millis(config().ANIMATE_INTERVAL_MS);
+            spawn(app, async move {
+                let mut ticks = tokio::time::interval(interval);
+                loop {
+                    tokio::select! {
+                        _ = token.cancelled() => break,
+                        _ = ticks.tick() => {
+                            if tx.send(Action::AnimateStep).is_err() {
+                                break;
+                            }
+                        }
+                    }
+                }
+            });
+        }
+        // one Increment or Decrement toward the target, stopping the task once there
+        Action::AnimateStep => match &app.animation {
+            Some((target, _)) if *target != app.counter => {
+                let step = if *target > app.counter {
+                    Action::Increment
+                } else {
+                    Action::Decrement
+                };
+                update(app, step);
+            }
+            Some(_) => {
+                if let Some((_, token)) = app.animation.take() {
+                    token.cancel();
+                }
+            }
+            None => {}
+        },
+        Action::CancelRequest => {
+            if let Some((_, token)) = app.pending_request.take() {
+                token.cancel();
+            }
+        }
+        Action::RequestFinished => {
+            app.pending_request = None;
+            bell(BellEvent::Request);
+        }
+        Action::LoadProducts => {
+            let Some(client) = client_for(app, Action::LoadProducts) else {
+                return;
+            };
+            let tx = app.action_tx.clone();
+            let query = app.query.clone();
+            spawn(app, async move {
+                let retry_tx = tx.clone();
+                let on_retry = |attempt| {
+                    let _ = retry_tx.send(Action::Retrying(attempt));
+                };
+                let _ = match products::load(&client, &query, on_retry).await {
+                    Ok(products) => tx.send(Action::ProductsLoaded(products)),
+                    Err(e) => tx.send(Action::RequestFailed(
+                        Box::new(Action::LoadProducts),
+                        e.to_string(),
+                    )),
+                };
+            });
+        }
+        Action::CheckConnection => {
+            let Some(client) = client_for(app, Action::CheckConnection) else {
+                return;
+            };
+            let tx = app.action_tx.clone();
+            spawn(app, async move {
+                let started = Instant::now();
+                let result = match tokio::time::timeout(PING_TIMEOUT, products::ping(&client)).await
+                {
+                    Ok(Ok(())) => Ok(started.elapsed()),
+                    Ok(Err(e)) => Err(e.to_string()),
+                    Err(_) => Err(format!("no answer within {}s", PING_TIMEOUT.as_secs())),
+                };
+                let _ = tx.send(Action::ConnectionChecked(result));
+            });
+        }
+        Action::ConnectionChecked(result) => {
+            app.db_connected = result.is_ok();
+            match result {
+                Ok(latency) => set_status(app, format!("ping ok ({} ms)", latency.as_millis())),
+                Err(e) => {
+                    app.last_failed = Some(Action::CheckConnection);
+                    set_status(app, format!("ping failed: {e}"));
+                }
+            }
+        }
+        Action::Retrying(attempt) => app.retry = Some(attempt),
+        Action::ProductsLoaded(mut products) => {
+            // the query asks for one row more than MAX_PRODUCTS to tell whether any were cut
+            app.products_truncated = config()
+                .MAX_PRODUCTS
+                .is_some_and(|max| products.len() > max);
+            if let Some(max) = config().MAX_PRODUCTS {
+                products.truncate(max);
+            }
+            app.startup_deadline = None;
+            app.reversed = false;
+            products::sort(&mut products);
+            app.loaded_products = products;
+            apply_filter(app);
+            app.last_loaded = Some(Instant::now());
+            app.retry = None;
+            app.db_connected = true;
+            app.last_error = None;
+            if app.loaded_products.is_empty() && config().EMPTY_IS_ERROR {
+                update(app, Action::Error("query returned no products".to_string()));
+            }
+        }
+        Action::SelectNext => {
+            let next = app.product_state.selected().map_or(0, |i| i + 1);
+            select(app, Some(next.min(app.products.len().saturating_sub(1))));
+        }
+        Action::SelectPrevious => {
+            let previous = app
+                .product_state
+                .selected()
+                .map_or(0, |i| i.saturating_sub(1));
+            select(app, Some(previous));
+        }
+        Action::SelectFirst => {
+            select(app, Some(0));
+            *app.product_state.offset_mut() = 0;
+        }
+        Action::SelectLast => select(app, Some(app.products.len().saturating_sub(1))),
+        // back to the top of the full list, the counter is left alone
+        Action::ClearView => {
+            if app.query.seller_range.take().is_some() {
+                let _ = app.action_tx.send(Action::LoadProducts);
+            }
+            app.filter.clear();
+            apply_filter(app);
+            app.overlays.close(Overlay::ProductDetail);
+            select(app, Some(0));
+            *app.product_state.offset_mut() = 0;
+            set_status(app, "view reset");
+        }
+        Action::OpenPrompt(kind) => app.prompt = Some(Prompt::new(kind)),
+        Action::PromptInput(c) => {
+            if let Some(prompt) = app.prompt.as_mut() {
+                prompt.input.push(c);
+            }
+            debounce_filter(app);
+        }
+        Action::PromptBackspace => {
+            if let Some(prompt) = app.prompt.as_mut() {
+                prompt.input.pop();
+            }
+            debounce_filter(app);
+        }
+        Action::ApplyFilter(filter) => {
+            app.filter_debounce = None;
+            if filter != app.filter {
+                app.filter = filter;
+                apply_filter(app);
+            }
+        }
+        Action::SubmitPrompt => {
+            if let Some(prompt) = app.prompt.take() {
+                submit_prompt(app, prompt);
+            }
+        }
+        Action::CancelPrompt => {
+            if let Some(token) = app.filter_debounce.take() {
+                token.cancel();
+            }
+            if app
+                .prompt
+                .take()
+                .is_some_and(|prompt| prompt.kind == PromptKind::Filter)
+            {
+                update(app, Action::ApplyFilter(String::new()));
+            }
+        }
+        Action::DuplicateSelected => {
+            if let Some(product) = selected_product(app).cloned() {
+                let Some(client) = client_for(app, Action::DuplicateSelected) else {
+                    return;
+                };
+                spawn_critical(app, "duplicating product", async move {
+                    match products::duplicate(&client, &product).await {
+                        Ok(code) => Action::ProductInserted(code),
+                        Err(message) => Action::Error(message),
+                    }
+                });
+            }
+        }
+        // flip the displayed order in memory, the loaded list too so filtering keeps it
+        // rebuilt from the loaded list so pinned products stay at the top
+        Action::ReverseList => {
+            app.loaded_products.reverse();
+            app.reversed = !app.reversed;
+            apply_filter(app);
+            set_status(
+                app,
+                if app.reversed {
+                    "reversed"
+                } else {
+                    "original order"
+                },
+            );
+        }
+        Action::TogglePersistCounter => app.persist_counter = !app.persist_counter,
+        Action::TogglePin => {
+            if let Some(code) = selected_product(app).map(|product| product.code.clone()) {
+                if !app.pinned.remove(&code) {
+                    app.pinned.insert(code);
+                }
+                sort_pinned(app);
+            }
+        }
+        Action::OpenProductUrl => {
+            if let Some(code) = selected_product(app).map(|product| product.code.clone()) {
+                let result = match &config().URL_TEMPLATE {
+                    Some(template) => browser::open(&template.replace("{code}", &code)),
+                    None => Err("URL_TEMPLATE is not set".to_string()),
+                };
+                match result {
+                    Ok(()) => set_status(app, format!("opened {code}")),
+                    Err(message) => update(app, Action::Error(message)),
+                }
+            }
+        }
+        Action::CopyVisibleCodes => {
+            let codes: Vec<&str> = app.products.iter().map(|p| p.code.as_str()).collect();
+            match clipboard::copy(&codes.join("\n")) {
+                Ok(target) => set_status(app, format!("copied {} codes to {target}", codes.len())),
+                Err(message) => update(app, Action::Error(message)),
+            }
+        }
+        Action::ExportSettings => match clipboard::copy(&settings_snippet(app)) {
+            Ok(target) => set_status(app, format!("settings copied to {target}")),
+            Err(message) => update(app, Action::Error(message)),
+        },
+        Action::ProductInserted(code) => {
+            set_status(app, format!("inserted product {code}"));
+            let _ = app.action_tx.send(Action::LoadProducts);
+        }
+        Action::Error(message) => {
+            tracing::error!(%message, "action failed");
+            app.retry = None;
+            bell(BellEvent::Error);
+            app.last_error = Some(message);
+        }
+        Action::RequestFailed(action, message) => {
+            app.startup_deadline = None;
+            app.last_failed = Some(*action);
+            update(app, Action::Error(message));
+        }
+        Action::RetryLast => match app.last_failed.take() {
+            Some(action) => {
+                set_status(app, format!("retrying {}", action.name()));
+                let _ = app.action_tx.send(action);
+            }
+            None => set_status(app, "nothing to retry"),
+        },
+        Action::ToggleDebug => app.overlays.toggle(Overlay::Debug),
+        Action::ToggleStepMode => app.step_mode = !app.step_mode,
+        Action::ToggleLogging => match logging::toggle() {
+            Ok(true) => {
+                tracing::info!("logging resumed");
+                set_status(app, "logging to LOG_FILE resumed");
+            }
+            Ok(false) => set_status(app, "logging to LOG_FILE paused"),
+            Err(message) => update(app, Action::Error(message)),
+        },
+        // pressing it again while boosted starts the duration over
+        Action::BoostFrameRate => {
+            app.fps_boost_until =
+                Some(Instant::now() + Duration::from_secs(config().FPS_BOOST_SECS));
+            set_status(
+                app,
+                format!(
+                    "{} fps for {}s",
+                    config().FPS_BOOST,
+                    config().FPS_BOOST_SECS
+                ),
+            );
+        }
+        Action::ToggleEventLog => app.overlays.toggle(Overlay::EventLog),
+        Action::ToggleTimeline => {
+            app.overlays.toggle(Overlay::Timeline);
+            app.timeline_scroll = 0;
+        }
+        Action::ScrollTimeline(by) => {
+            let max = app.event_log.lines().len().saturating_sub(1);
+            app.timeline_scroll = app.timeline_scroll.saturating_add_signed(by).min(max);
+        }
+        Action::CycleEventLogLevel => {
+            app.event_log_level = app.event_log_level.next();
+            set_status(
+                app,
+                format!("event log level {}", app.event_log_level.label()),
+            );
+        }
+        Action::ExportEventLog => match app.event_log.export() {
+            Ok(path) => set_status(app, format!("event log written to {path}")),
+            Err(message) => update(app, Action::Error(message)),
+        },
+        Action::CompactMemory => {
+            let layouts = app.layout_cache.len();
+            let bytes = app.event_log.compact()
+                + app.layout_cache.compact()
+                + shrink(&mut app.products)
+                + shrink(&mut app.loaded_products)
+                + shrink(&mut app.bookmarks);
+            set_status(
+                app,
+                format!(
+                    "released {bytes} bytes of spare capacity, dropped {layouts} cached layouts"
+                ),
+            );
+        }
+        Action::CaptureScreen => match capture_screen(app) {
+            Ok(path) => set_status(app, format!("screen written to {path}")),
+            Err(message) => update(app, Action::Error(message)),
+        },
+        Action::CycleKeymap => {
+            app.keymaps.cycle();
+            set_status(app, format!("keymap {}", app.keymaps.name()));
+        }
+        Action::ToggleKeyInspector => {
+            app.key_inspector = !app.key_inspector;
+            app.last_key = None;
+        }
+        Action::InspectKey(key) => app.last_key = Some(key),
+        Action::CloseOverlay => {
+            app.overlays.pop();
+        }
+        Action::ShowAbout => app.overlays.open(Overlay::About),
+        Action::ShowHelp => app.overlays.open(Overlay::Help),
+        Action::HideHelp => app.overlays.close(Overlay::Help),
+        Action::ShowConfig => app.overlays.open(Overlay::Config),
+        Action::HideConfig => app.overlays.close(Overlay::Config),
+        Action::ShowSellerHistogram => app.overlays.open(Overlay::SellerHistogram),
+        Action::HideSellerHistogram => app.overlays.close(Overlay::SellerHistogram),
+        Action::ToggleBookmarks => {
+            app.overlays.toggle(Overlay::Bookmarks);
+            let first = (!app.bookmarks.is_empty()).then_some(0);
+            app.bookmark_state.select(first);
+        }
+        Action::SelectNextBookmark => {
+            if let Some(i) = app.bookmark_state.selected() {
+                app.bookmark_state
+                    .select(Some((i + 1).min(app.bookmarks.len() - 1)));
+            }
+        }
+        Action::SelectPreviousBookmark => {
+            if let Some(i) = app.bookmark_state.selected() {
+                app.bookmark_state.select(Some(i.saturating_sub(1)));
+            }
+        }
+        Action::ApplyBookmark => {
+            let selected = app.bookmark_state.selected();
+            if let Some((label, value)) = selected.and_then(|i| app.bookmarks.get(i)).cloned() {
+                app.overlays.close(Overlay::Bookmarks);
+                update(app, Action::SetCounter(value));
+                set_status(app, format!("counter set to {label}"));
+            }
+        }
+        Action::HideAbout => app.overlays.close(Overlay::About),
+        Action::ShowProductDetail if selected_product(app).is_some() => {
+            app.overlays.open(Overlay::ProductDetail)
+        }
+        Action::HideProductDetail => app.overlays.close(Overlay::ProductDetail),
+        Action::ToggleBorders => app.show_borders = !app.show_borders,
+        Action::ToggleWrap => app.wrap_text = !app.wrap_text,
+        Action::ToggleCompact => app.compact = !app.compact,
+        Action::ToggleHighContrast => app.high_contrast = !app.high_contrast,
+        Action::ToggleView => {
+            app.product_view = match app.product_view {
+                ProductView::List => ProductView::Table,
+                ProductView::Table => ProductView::List,
+            }
+        }
+        Action::ToggleLayout => {
+            app.layout = match app.layout {
+                LayoutOrientation::Vertical => LayoutOrientation::Horizontal,
+                LayoutOrientation::Horizontal => LayoutOrientation::Vertical,
+            }
+        }
+        Action::FocusNext => {
+            app.focus = match app.focus {
+                Pane::Counter => Pane::Products,
+                Pane::Products => Pane::Counter,
+            }
+        }
+        Action::FocusPane(pane) => app.focus = pane,
+        Action::ToggleProductsFullscreen => app.products_fullscreen = !app.products_fullscreen,
+        // these take effect on the next tick, which refreshes the displayed time
+        Action::ToggleSeconds => app.show_seconds = !app.show_seconds,
+        Action::ToggleTimestampMode => {
+            app.timestamp_mode = match app.timestamp_mode {
+                TimestampMode::Absolute => TimestampMode::Relative,
+                TimestampMode::Relative => TimestampMode::Absolute,
+            }
+        }
+        Action::CycleTimezone => {
+            app.timezone = (app.timezone + 1) % app.live.TIMEZONES.len();
+            set_status(app, format!("timezone {}", timezone(app).name()));
+        }
+        // nothing is applied unless the config and every keymap load
+        Action::ReloadConfig => {
+            match LiveConfig::load_from_env()
+                .map_err(|e| e.to_string())
+                .and_then(|live| Keymaps::load(&live).map(|keymaps| (live, keymaps)))
+            {
+                Ok((live, keymaps)) => reload_config(app, live, keymaps),
+                Err(e) => update(app, Action::Error(format!("config reload failed: {e}"))),
+            }
+        }
+        Action::CycleTheme => {
+            app.theme = (app.theme + 1) % THEMES.len();
+            set_status(app, format!("theme: {}", THEMES[app.theme].0));
+        }
+        // unanswered confirmations cancel themselves, the countdown redraws every frame
+        Action::Render if app.confirm_quit.is_some() => {
+            if confirm_remaining(app).is_some_and(|remaining| remaining.is_zero()) {
+                app.confirm_quit = None;
+                bell(BellEvent::Timer);
+            }
+            app.dirty = true;
+        }
+        // expiring the status message is itself a visual change
+        Action::Render
+            if app
+                .status
+                .as_ref()
+                .is_some_and(|(_, set_at)| set_at.elapsed() >= STATUS_DURATION) =>
+        {
+            app.status = None;
+            app.dirty = true;
+        }
+        Action::AskQuit if config().CONFIRM_QUIT => app.confirm_quit = Some(Instant::now()),
+        Action::AskQuit | Action::Quit => {
+            app.should_quit = true;
+            bell(BellEvent::Quit);
+        }
+        Action::CancelQuit => app.confirm_quit = None,
+        // the next draw sees the new size anyway, this just frees the old rects early
+        Action::Resize => app.layout_cache.clear(),
+        _ => {}
+    };
+}
+// ANCHOR_END: update
 
-// App ui render function
-fn ui(f: &mut Frame, app: &mut App) {
-    let area = f.size();
-    f.render_widget(
-        Paragraph::new(Text::from(vec![
-            Line::from("Press j or k to increment or decrement."),
-            Line::from(format!("Counter: {}", app.counter)),
-            Line::from(format!("last updated {:?}", app.refresh_datetime)),
-        ]))
-        .block(
-            Block::default()
-                .title("ratatui async counter app")
-                .title_alignment(Alignment::Center)
-                .borders(Borders::ALL)
-                .border_type(BorderType::Rounded),
-        )
-        .style(Style::default().fg(Color::Cyan))
-        .alignment(Alignment::Center),
-        area,
+fn submit_prompt(app: &mut App, prompt: Prompt) {
+    match prompt.kind {
+        PromptKind::SellerRange => match parse_seller_range(&prompt.input) {
+            Ok(range) => {
+                app.query.seller_range = range;
+                // narrow what is on screen right away, the reload brings in the rest
+                apply_filter(app);
+                if range.is_some() {
+                    select(app, Some(0));
+                }
+                let _ = app.action_tx.send(Action::LoadProducts);
+            }
+            Err(message) => update(app, Action::Error(message)),
+        },
+        PromptKind::Command => match command::parse(&prompt.input) {
+            Ok(action) => update(app, action),
+            Err(message) => update(app, Action::Error(message)),
+        },
+        PromptKind::SimulateDelay => match prompt.input.trim().parse::<u64>() {
+            Ok(ms) => update(app, Action::SimulateDelay(ms)),
+            Err(_) => update(
+                app,
+                Action::Error(format!("not a duration in ms: {:?}", prompt.input)),
+            ),
+        },
+        PromptKind::Bookmark => {
+            let label = prompt.input.trim();
+            if label.is_empty() {
+                update(
+                    app,
+                    Action::Error("bookmark label must not be empty".to_string()),
+                );
+            } else {
+                app.bookmarks.push((label.to_string(), app.counter));
+                set_status(app, format!("bookmarked {} as {label}", app.counter));
+            }
+        }
+        PromptKind::GoToCode => {
+            let code = prompt.input.trim();
+            match app.products.iter().position(|product| product.code == code) {
+                Some(index) => select(app, Some(index)),
+                None => set_status(app, format!("{code} not found")),
+            }
+        }
+        PromptKind::Filter => {
+            if let Some(token) = app.filter_debounce.take() {
+                token.cancel();
+            }
+            update(app, Action::ApplyFilter(prompt.input.trim().to_string()));
+        }
+        PromptKind::AnimateTo => match prompt.input.trim().parse::<i64>() {
+            Ok(target) => update(app, Action::AnimateTo(target)),
+            Err(_) => update(
+                app,
+                Action::Error(format!("not a number: {:?}", prompt.input)),
+            ),
+        },
+        PromptKind::AddAmount => match prompt.input.trim().parse::<i64>() {
+            Ok(amount) => update(app, Action::SetCounter(apply_delta(app.counter, amount))),
+            Err(_) => update(
+                app,
+                Action::Error(format!("not a number: {:?}", prompt.input)),
+            ),
+        },
+        PromptKind::NewProduct => {
+            let product = match parse_product(&prompt.input).and_then(products::validate) {
+                Ok(product) => product,
+                Err(message) => return update(app, Action::Error(message)),
+            };
+            let Ok(client) = app.client.clone() else {
+                return update(app, Action::Error(not_connected(app)));
+            };
+            // ENFORCE_UNIQUE_CODE checks right before inserting, in the same task. Another
+            // client can still insert in between, only a unique index rules that out.
+            spawn_critical(app, "inserting product", async move {
+                if config().ENFORCE_UNIQUE_CODE {
+                    match products::code_exists(&client, &product.code).await {
+                        Ok(true) => {
+                            return Action::Error(format!(
+                                "a product with code {} already exists",
+                                product.code
+                            ))
+                        }
+                        Ok(false) => {}
+                        Err(e) => return Action::Error(e.to_string()),
+                    }
+                }
+                match products::insert(&client, &product).await {
+                    Ok(()) => Action::ProductInserted(product.code),
+                    Err(e) => Action::Error(e.to_string()),
+                }
+            });
+        }
+    }
+}
+
+// "10-50" or a single "10", both inclusive. Empty input clears the range.
+fn parse_seller_range(input: &str) -> std::result::Result<Option<(i32, i32)>, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Ok(None);
+    }
+    let invalid = || format!("invalid seller range {input:?}, expected min-max");
+    // a leading '-' is the sign of the lower bound, not the separator
+    let separator = input.char_indices().skip(1).find(|&(_, c)| c == '-');
+    let (min, max) = match separator {
+        Some((i, _)) => (&input[..i], &input[i + 1..]),
+        None => (input, input),
+    };
+    let min: i32 = min.trim().parse().map_err(|_| invalid())?;
+    let max: i32 = max.trim().parse().map_err(|_| invalid())?;
+    if min > max {
+        return Err(invalid());
+    }
+    Ok(Some((min, max)))
+}
+
+// "name, code, seller_id", split from the right so the name may contain commas
+fn parse_product(input: &str) -> std::result::Result<Products, String> {
+    let mut parts = input.rsplitn(3, ',').map(str::trim);
+    let (Some(seller_id), Some(code), Some(name)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Err("expected: name, code, seller_id".to_string());
+    };
+    let seller_id = seller_id
+        .parse()
+        .map_err(|_| format!("seller_id must be a number, got {seller_id:?}"))?;
+    Ok(Products {
+        name: name.to_string(),
+        code: code.to_string(),
+        seller_id,
+    })
+}
+
+// The database client for a request, or `action` reported as failed when there is none
+fn client_for(app: &App, action: Action) -> Option<Client> {
+    match &app.client {
+        Ok(client) => Some(client.clone()),
+        Err(_) => {
+            let _ = app
+                .action_tx
+                .send(Action::RequestFailed(Box::new(action), not_connected(app)));
+            None
+        }
+    }
+}
+
+fn not_connected(app: &App) -> String {
+    match &app.client {
+        Ok(_) => String::new(),
+        Err(reason) => format!("not connected: {reason}"),
+    }
+}
+
+// Give back a vector's spare capacity, returning how many bytes that released
+fn shrink<T>(items: &mut Vec<T>) -> usize {
+    let capacity = items.capacity();
+    items.shrink_to_fit();
+    (capacity - items.capacity()) * std::mem::size_of::<T>()
+}
+
+// Spawn a background task and keep its handle, so the debug overlay can count the ones
+// still running
+fn spawn<F>(app: &mut App, task: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    app.tasks.retain(|task| !task.is_finished());
+    app.tasks.push(tokio::spawn(task));
+}
+
+// Run a request that must not overlap with other input. Until it finishes or is
+// cancelled, a modal is shown and get_action ignores every key but Esc.
+fn spawn_critical<F>(app: &mut App, label: &str, request: F)
+where
+    F: Future<Output = Action> + Send + 'static,
+{
+    if app.pending_request.is_some() {
+        return;
+    }
+    let token = CancellationToken::new();
+    app.pending_request = Some((label.to_string(), token.clone()));
+
+    let tx = app.action_tx.clone();
+    spawn(app, critical_task(tx, token, request));
+}
+
+// The task behind `spawn_critical`, ends on cancellation or once the result is sent
+async fn critical_task<F>(tx: channel::Sender<Action>, token: CancellationToken, request: F)
+where
+    F: Future<Output = Action>,
+{
+    tokio::select! {
+        _ = token.cancelled() => {}
+        action = request => {
+            // the receiver is gone once the app is shutting down
+            let _ = tx.send(action);
+            let _ = tx.send(Action::RequestFinished);
+        }
+    }
+}
+
+// Add `delta` to the counter within COUNTER_MIN/COUNTER_MAX, see `bounded_add`
+fn apply_delta(counter: i64, delta: i64) -> i64 {
+    bounded_add(
+        counter,
+        delta,
+        config().COUNTER_MIN,
+        config().COUNTER_MAX,
+        config().COUNTER_WRAP,
+    )
+}
+
+// Out of range values are clamped, or with `wrap` and both bounds set, wrap around to
+// the other end
+fn bounded_add(counter: i64, delta: i64, min: Option<i64>, max: Option<i64>, wrap: bool) -> i64 {
+    if let (true, Some(min), Some(max)) = (wrap, min, max) {
+        let span = max as i128 - min as i128 + 1;
+        let offset = (counter as i128 + delta as i128 - min as i128).rem_euclid(span);
+        return (min as i128 + offset) as i64;
+    }
+    let next = counter.saturating_add(delta);
+    next.clamp(min.unwrap_or(i64::MIN), max.unwrap_or(i64::MAX))
+}
+
+// Time left before the quit confirmation cancels itself, None when it never times out
+fn confirm_remaining(app: &App) -> Option<Duration> {
+    let opened = app.confirm_quit?;
+    match config().CONFIRM_TIMEOUT_SECS {
+        0 => None,
+        secs => Some(Duration::from_secs(secs).saturating_sub(opened.elapsed())),
+    }
+}
+
+fn report_slow_frame(app: &mut App, frame: Duration) {
+    let Some(threshold) = config().SLOW_FRAME_MS else {
+        return;
+    };
+    if frame > Duration::from_millis(threshold) {
+        tracing::warn!(frame_ms = frame.as_millis() as u64, "slow frame");
+        if config().SLOW_FRAME_FLASH {
+            set_status(app, format!("slow frame: {}ms", frame.as_millis()));
+            app.dirty = true;
+        }
+    }
+}
+
+// Render `ui` at the terminal's size into an off-screen buffer and write its text, no
+// colors, to SCREEN_CAPTURE_PATH or a timestamped file in the temp dir
+fn capture_screen(app: &mut App) -> std::result::Result<String, String> {
+    let (width, height) = crossterm::terminal::size().map_err(|e| e.to_string())?;
+    let mut terminal =
+        Terminal::new(backend::TestBackend::new(width, height)).map_err(|e| e.to_string())?;
+    terminal.draw(|f| ui(f, app)).map_err(|e| e.to_string())?;
+
+    let buffer = terminal.backend().buffer();
+    let mut contents = String::new();
+    for y in 0..buffer.area.height {
+        let mut line = String::new();
+        let mut x = 0;
+        while x < buffer.area.width {
+            let symbol = buffer.get(x, y).symbol();
+            line.push_str(symbol);
+            // a wide character covers the cells after it
+            x += symbol.width().max(1) as u16;
+        }
+        contents.push_str(line.trim_end());
+        contents.push('\n');
+    }
+
+    let path = config()
+        .SCREEN_CAPTURE_PATH
+        .as_ref()
+        .map(std::path::PathBuf::from)
+        .unwrap_or_else(|| {
+            let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+            std::env::temp_dir().join(format!("terminal-ui-screen-{stamp}.txt"))
+        });
+    std::fs::write(&path, contents).map_err(|e| format!("{}: {e}", path.display()))?;
+    Ok(path.display().to_string())
+}
+
+// Ring the terminal bell if BELL_ON lists the event. The terminal draws to stderr,
+// so the bell goes there too.
+fn bell(event: BellEvent) {
+    if config().BELL_ON.contains(&event) {
+        let mut stderr = std::io::stderr();
+        let _ = stderr.write_all(b"\x07").and_then(|()| stderr.flush());
+    }
+}
+
+// Everything except the periodic and no-op actions goes into the event log, and
+// scrolling the timeline would only push it along
+fn record_event(app: &mut App, action: &Action) {
+    let (level, message) = match action {
+        Action::Tick | Action::Render | Action::None | Action::ScrollTimeline(_) => return,
+        Action::Error(message) | Action::RequestFailed(_, message) => {
+            (LogLevel::Error, format!("Error: {message}"))
+        }
+        Action::ConnectionChecked(Err(message)) => {
+            (LogLevel::Warn, format!("ConnectionChecked: {message}"))
+        }
+        Action::Retrying(attempt) => (LogLevel::Warn, format!("Retrying: attempt {attempt}")),
+        Action::CancelRequest | Action::AskQuit | Action::Quit => {
+            (LogLevel::Warn, action.name().to_string())
+        }
+        _ => (LogLevel::Info, action.name().to_string()),
+    };
+    app.event_log.push(
+        level,
+        &current_datetime(timezone(app), StrftimeItems::new(&config().DATETIME_FORMAT)),
+        &message,
     );
 }
 
-// ANCHOR: get_action
-fn get_action(_app: &App, event: Event) -> Action {
-    match event {
-        Event::Error => Action::None,
-        Event::Tick => Action::Tick,
-        Event::Render => Action::Render,
-        Event::Key(key) => {
-            match key.code {
-                Char('j') => Action::Increment,
-                Char('k') => Action::Decrement,
-                Char('J') => Action::NetworkRequestAndThenIncrement, // new
-                Char('K') => Action::NetworkRequestAndThenDecrement, // new
-                Char('q') => Action::Quit,
-                _ => Action::None,
+fn timezone(app: &App) -> Tz {
+    app.live.TIMEZONES[app.timezone]
+}
+
+// DATETIME_FORMAT adjusted by the display toggles
+fn datetime_format(app: &App) -> Vec<Item<'static>> {
+    if app.show_seconds {
+        StrftimeItems::new(&config().DATETIME_FORMAT).collect()
+    } else {
+        without_seconds(&config().DATETIME_FORMAT)
+    }
+}
+
+// The seconds field and a ':' right before it left out. This works on the parsed items,
+// so escapes like "%%S" stay literal text and the result is as valid as `format`.
+fn without_seconds(format: &str) -> Vec<Item<'_>> {
+    let mut items = Vec::new();
+    for item in StrftimeItems::new(format) {
+        if matches!(item, Item::Numeric(Numeric::Second, _)) {
+            if let Some(Item::Literal(literal)) = items.last_mut() {
+                *literal = literal.strip_suffix(':').unwrap_or(literal);
             }
+            continue;
         }
-        _ => Action::None,
+        items.push(item);
     }
+    items
 }
-// ANCHOR_END: get_action
 
-// ANCHOR: update
-fn update(app: &mut App, action: Action) {
-    match action {
-        Action::Tick => {
-            let now = Utc::now();
+fn current_datetime<'a>(timezone: Tz, format: impl Iterator<Item = Item<'a>> + Clone) -> String {
+    Utc::now()
+        .with_timezone(&timezone)
+        .format_with_items(format)
+        .to_string()
+}
 
-            let kst = now
-                .with_timezone(&Seoul)
-                .format("%Y-%m-%d %H:%M:%S")
-                .to_string();
+// Pinned products first, otherwise keeping the loaded order. The selection follows the
+// selected product to its new position.
+fn sort_pinned(app: &mut App) {
+    let selected = selected_product(app).map(|product| product.code.clone());
+    let pinned = &app.pinned;
+    app.products
+        .sort_by_key(|product| !pinned.contains(&product.code));
+    if let Some(code) = selected {
+        let index = app.products.iter().position(|product| product.code == code);
+        select(app, index);
+    }
+}
+
+fn selected_product(app: &App) -> Option<&Products> {
+    app.product_state
+        .selected()
+        .and_then(|i| app.products.get(i))
+}
 
-            app.refresh_datetime = kst;
+// While the filter prompt is open, apply its input once no key has arrived for
+// FILTER_DEBOUNCE_MS. The prompt itself shows every key straight away.
+fn debounce_filter(app: &mut App) {
+    let Some(prompt) = app.prompt.as_ref().filter(|p| p.kind == PromptKind::Filter) else {
+        return;
+    };
+    let filter = prompt.input.trim().to_string();
+    if let Some(token) = app.filter_debounce.take() {
+        token.cancel();
+    }
+    let token = CancellationToken::new();
+    app.filter_debounce = Some(token.clone());
+    let tx = app.action_tx.clone();
+    let quiet = Duration::from_millis(config().FILTER_DEBOUNCE_MS);
+    spawn(app, async move {
+        tokio::select! {
+            _ = token.cancelled() => {}
+            _ = tokio::time::sleep(quiet) => {
+                let _ = tx.send(Action::ApplyFilter(filter));
+            }
         }
-        Action::Increment => {
-            app.counter += 1;
+    });
+}
+
+// Rebuild `products` from the loaded list, keeping names or codes that contain the
+// filter (ignoring case) within the seller range, pinned first, and the selection on
+// the same code if it is still listed. The range is also part of the query, this keeps
+// rows loaded before it was set out until the next load.
+fn apply_filter(app: &mut App) {
+    let selected = selected_product(app).map(|product| product.code.clone());
+    let filter = app.filter.to_lowercase();
+    let range = app.query.seller_range;
+    app.products = app
+        .loaded_products
+        .iter()
+        .filter(|product| {
+            product.name.to_lowercase().contains(&filter)
+                || product.code.to_lowercase().contains(&filter)
+        })
+        .filter(|product| range.is_none_or(|(min, max)| (min..=max).contains(&product.seller_id)))
+        .cloned()
+        .collect();
+    sort_pinned(app);
+    let index = selected
+        .and_then(|code| app.products.iter().position(|product| product.code == code))
+        .or(app.product_state.selected())
+        .map(|i| i.min(app.products.len().saturating_sub(1)));
+    select(app, index);
+}
+
+// Select a product row, clearing the selection when there is nothing to select.
+// The list keeps its scroll offset so that the selection stays visible on render.
+fn select(app: &mut App, index: Option<usize>) {
+    if app.products.is_empty() {
+        app.product_state.select(None);
+    } else {
+        app.product_state.select(index);
+    }
+}
+
+// Apply the live settings that differ and name them on the status line
+fn reload_config(app: &mut App, live: LiveConfig, mut keymaps: Keymaps) {
+    let mut changed = Vec::new();
+    if live.THEME != app.live.THEME {
+        app.theme = theme_index(&live.THEME);
+        changed.push("theme");
+    }
+    if live.TIMEZONES != app.live.TIMEZONES {
+        // the zone on screen is kept when the new list still has it
+        let current = timezone(app);
+        app.timezone = live
+            .TIMEZONES
+            .iter()
+            .position(|zone| *zone == current)
+            .unwrap_or_default();
+        changed.push("timezones");
+    }
+    if live.MAX_FPS != app.live.MAX_FPS {
+        changed.push("max fps");
+    }
+    // picked up by the run loop, which owns the tui
+    if live.TICK_RATE != app.live.TICK_RATE || live.FRAME_RATE != app.live.FRAME_RATE {
+        app.rates_changed = true;
+        changed.push("rates");
+    }
+    if !keymaps.same_profiles(&app.keymaps) {
+        // the active profile stays active when it still exists
+        keymaps.select(app.keymaps.name());
+        app.keymaps = keymaps;
+        changed.push("keymaps");
+    }
+    app.live = live;
+    if changed.is_empty() {
+        set_status(app, "config reloaded, nothing changed");
+    } else {
+        set_status(app, format!("config reloaded: {}", changed.join(", ")));
+    }
+}
+
+fn theme_index(name: &str) -> usize {
+    THEMES
+        .iter()
+        .position(|(theme, _)| theme.eq_ignore_ascii_case(name))
+        .unwrap_or_default()
+}
+
+enum StartupWait {
+    Connected,
+    AuthFailed,
+    Offline(String),
+    // STARTUP_TIMEOUT_SECS ran out before CONNECT_WAIT_SECS did
+    TimedOut,
+}
+
+// Ping the database for up to CONNECT_WAIT_SECS, or what is left of
+// STARTUP_TIMEOUT_SECS if that is less, while showing a spinner. Esc gives up early.
+async fn wait_for_database(
+    tui: &mut tui::Tui,
+    client: &Client,
+    startup: Instant,
+) -> Result<StartupWait> {
+    let started = Instant::now();
+    let ping = products::ping(client);
+    let connect_wait = Duration::from_secs(config().CONNECT_WAIT_SECS);
+    let (limit, timed_out) = match startup_remaining(startup) {
+        Some(remaining) if remaining < connect_wait => (remaining, true),
+        _ => (connect_wait, false),
+    };
+    let deadline = tokio::time::sleep(limit);
+    tokio::pin!(ping, deadline);
+    loop {
+        tokio::select! {
+            result = &mut ping => {
+                return Ok(match result {
+                    Ok(()) => StartupWait::Connected,
+                    Err(e) if products::is_auth_error(&e) => StartupWait::AuthFailed,
+                    Err(e) => StartupWait::Offline(e.to_string()),
+                });
+            }
+            _ = &mut deadline => {
+                if timed_out {
+                    return Ok(StartupWait::TimedOut);
+                }
+                return Ok(StartupWait::Offline("mongodb did not answer in time".to_string()));
+            }
+            event = tui.next() => match event? {
+                tui::Event::Key(key) if key.code == KeyCode::Esc => {
+                    return Ok(StartupWait::Offline("connection wait skipped".to_string()));
+                }
+                tui::Event::Render => {
+                    let step = (started.elapsed().as_millis() / SPINNER_STEP.as_millis()) as usize;
+                    let spinner = SPINNER[step % SPINNER.len()];
+                    tui.draw(|f| connecting_screen(f, spinner, THEMES[theme_index(&config().THEME)].1))?;
+                }
+                _ => {}
+            },
         }
-        Action::Decrement => {
-            app.counter -= 1;
+    }
+}
+
+// Time left of STARTUP_TIMEOUT_SECS, counted from `startup`, if it is set
+fn startup_remaining(startup: Instant) -> Option<Duration> {
+    let timeout = Duration::from_secs(config().STARTUP_TIMEOUT_SECS?);
+    Some(timeout.saturating_sub(startup.elapsed()))
+}
+
+// STARTUP_TIMEOUT_ACTION=exit: leave the ui and exit with an error naming the phase
+fn startup_timed_out(tui: &mut tui::Tui, phase: &str) -> ! {
+    let _ = tui.exit();
+    eprintln!(
+        "startup did not finish within {}s ({phase})",
+        config().STARTUP_TIMEOUT_SECS.unwrap_or_default()
+    );
+    std::process::exit(1);
+}
+
+// Without a terminal, NO_TTY=print writes the counter to stdout, the saved one with
+// PERSIST_COUNTER, and anything else is an error
+async fn no_tty() -> ! {
+    if config().NO_TTY == NoTtyAction::Exit {
+        eprintln!("not running in a terminal, set NO_TTY=print to print the counter instead");
+        std::process::exit(1);
+    }
+
+    let saved = if config().PERSIST_COUNTER && !config().OFFLINE {
+        saved_counter(async { products::load_counter(&products::connect().await?).await }).await
+    } else {
+        None
+    };
+    println!(
+        "{}",
+        initial_counter(saved, config().PERSIST_COUNTER, config().INITIAL_COUNTER)
+    );
+    std::process::exit(0);
+}
+
+// The counter saved by PERSIST_COUNTER, None when there is none or it could not be
+// loaded within PING_TIMEOUT
+async fn saved_counter(
+    load: impl Future<Output = mongodb::error::Result<Option<i64>>>,
+) -> Option<i64> {
+    match tokio::time::timeout(PING_TIMEOUT, load).await {
+        Ok(Ok(saved)) => saved,
+        Ok(Err(e)) => {
+            tracing::warn!(error = %e, "could not load the saved counter");
+            None
         }
-        Action::NetworkRequestAndThenIncrement => {
-            let tx = app.action_tx.clone();
-            tokio::spawn(async move {
-                tokio::time::sleep(Duration::from_secs(5)).await; // simulate network request
-                tx.send(Action::Increment).unwrap();
-            });
+        Err(_) => {
+            tracing::warn!("loading the saved counter timed out");
+            None
         }
-        Action::NetworkRequestAndThenDecrement => {
-            let tx = app.action_tx.clone();
-            tokio::spawn(async move {
-                tokio::time::sleep(Duration::from_secs(5)).await; // simulate network request
-                tx.send(Action::Decrement).unwrap();
-            });
+    }
+}
+
+// Queue an action for the run loop. A closed channel means nothing can be processed
+// anymore, so the loop should stop and shut down cleanly rather than fail.
+fn dispatch(action_tx: &channel::Sender<Action>, action: Action) -> ControlFlow<()> {
+    match action_tx.send(action) {
+        Ok(()) => ControlFlow::Continue(()),
+        Err(_) => {
+            tracing::warn!("action channel closed, shutting down");
+            ControlFlow::Break(())
         }
-        Action::Quit => app.should_quit = true,
-        _ => {}
-    };
+    }
+}
+
+// With PERSIST_COUNTER a saved counter wins over INITIAL_COUNTER, which defaults to 0
+fn initial_counter(saved: Option<i64>, persist: bool, initial: i64) -> i64 {
+    match saved {
+        Some(saved) if persist => saved,
+        _ => initial,
+    }
 }
-// ANCHOR_END: update
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct Products {
-    pub name: String,
-    pub code: String,
-    pub seller_id: i32,
+// The terminal is restored first so the message lands on the normal screen
+fn auth_failed(tui: &mut tui::Tui) -> ! {
+    let _ = tui.exit();
+    eprintln!(
+        "authentication failed—check credentials ({})",
+        config().redacted_mongo_uri()
+    );
+    std::process::exit(1);
 }
 
 // ANCHOR: run
-async fn run() -> Result<()> {
-    let (action_tx, mut action_rx) = mpsc::unbounded_channel(); // new
+// `startup` is when the process started, STARTUP_TIMEOUT_SECS counts from there so the
+// config load is included
+async fn run(startup: Instant) -> Result<()> {
+    let keymaps =
+        Keymaps::load(&LiveConfig::from(config())).map_err(|e| color_eyre::eyre::eyre!(e))?;
+    let (action_tx, mut action_rx) = channel::channel(config().ACTION_CHANNEL_CAPACITY); // new
+
+    // raw mode needs a terminal on stdin and the ui draws to stderr
+    if !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+        no_tty().await;
+    }
 
     // ratatui terminal
-    let mut tui = tui::Tui::new()?.tick_rate(0.1).frame_rate(30.0);
+    let mut tui = tui::Tui::new()?
+        .tick_rate(config().TICK_RATE)
+        .frame_rate(config().FRAME_RATE)
+        .mouse(true)
+        .key_repeat(config().KEY_REPEAT);
     tui.enter()?;
 
-    let client_options = ClientOptions::parse(&config().MONGO_URI).await.unwrap();
-    let client = Client::with_options(client_options).unwrap();
+    // wait for the database behind a spinner. A typo'd password gets a readable message
+    // instead of a panic, while an unreachable server is not fatal: the ui starts
+    // offline and the product load reports the error. So is a URI that does not even
+    // resolve, only then there is no client and every request fails with the reason.
+    let client = match products::connect().await {
+        Ok(client) => Ok(client),
+        Err(e) if products::is_auth_error(&e) => auth_failed(&mut tui),
+        Err(e) => Err(e.to_string()),
+    };
+    // OFFLINE starts without waiting for, or loading from, the database
+    let wait = match &client {
+        _ if config().OFFLINE => StartupWait::Offline("OFFLINE is set".to_string()),
+        Err(reason) => StartupWait::Offline(reason.clone()),
+        _ if startup_remaining(startup) == Some(Duration::ZERO) => StartupWait::TimedOut,
+        Ok(client) => wait_for_database(&mut tui, client, startup).await?,
+    };
+    let offline = match wait {
+        StartupWait::Connected => None,
+        StartupWait::AuthFailed => auth_failed(&mut tui),
+        StartupWait::Offline(reason) => Some(reason),
+        StartupWait::TimedOut => match config().STARTUP_TIMEOUT_ACTION {
+            StartupTimeoutAction::Exit => startup_timed_out(&mut tui, "connecting"),
+            StartupTimeoutAction::Offline => Some("startup timed out".to_string()),
+        },
+    };
 
-    let now = Utc::now();
+    let saved = match &client {
+        Ok(client) if config().PERSIST_COUNTER && offline.is_none() => {
+            saved_counter(products::load_counter(client)).await
+        }
+        _ => None,
+    };
+    let initial_counter =
+        initial_counter(saved, config().PERSIST_COUNTER, config().INITIAL_COUNTER);
 
-    let kst = now
-        .with_timezone(&Seoul)
-        .format("%Y-%m-%d %H:%M:%S")
-        .to_string();
+    // banner is read once here, a missing file just means no banner
+    let banner = config()
+        .BANNER_PATH
+        .as_ref()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .map(|contents| contents.lines().map(String::from).collect())
+        .unwrap_or_default();
 
     // application state
     let mut app = App {
-        counter: 0,
+        counter: initial_counter,
         should_quit: false,
         action_tx: action_tx.clone(),
         client,
-        refresh_datetime: kst,
+        db_connected: offline.is_none(),
+        refresh_datetime: current_datetime(
+            config().TIMEZONES[0],
+            StrftimeItems::new(&config().DATETIME_FORMAT),
+        ),
+        refreshed_at: Instant::now(),
+        timestamp_mode: TimestampMode::Absolute,
+        relative_shown: None,
+        timezone: 0,
+        show_seconds: true,
+        products: Vec::new(),
+        loaded_products: Vec::new(),
+        products_truncated: false,
+        filter: String::new(),
+        reversed: false,
+        persist_counter: config().PERSIST_COUNTER,
+        filter_debounce: None,
+        product_state: ListState::default(),
+        last_error: offline.map(|e| format!("offline: {e}")),
+        last_failed: None,
+        animation: None,
+        request_frame_drawn: false,
+        startup_deadline: None,
+        tasks: Vec::new(),
+        prompt: None,
+        confirm_quit: None,
+        pending_request: None,
+        status: None,
+        pulse_started: Instant::now(),
+        theme: theme_index(&config().THEME),
+        live: LiveConfig::from(config()),
+        overlays: Overlays::default(),
+        timeline_scroll: 0,
+        event_log: EventLog::default(),
+        layout_cache: LayoutCache::default(),
+        keymaps,
+        event_log_level: config().EVENT_LOG_LEVEL,
+        key_inspector: false,
+        last_key: None,
+        compact: config().COMPACT,
+        high_contrast: config().HIGH_CONTRAST,
+        color_mode: match config().COLOR_MODE {
+            ColorMode::Auto => color::detect(),
+            mode => mode,
+        },
+        bookmarks: Vec::new(),
+        bookmark_state: ListState::default(),
+        products_fullscreen: config().FULLSCREEN,
+        focus: Pane::Counter,
+        pane_areas: Vec::new(),
+        show_borders: config().SHOW_BORDERS,
+        wrap_text: config().WRAP_TEXT,
+        layout: config().LAYOUT,
+        product_view: config().PRODUCT_VIEW,
+        banner,
+        action_timings: HashMap::new(),
+        queue_depth: 0,
+        peak_queue_depth: 0,
+        rate_slowed: false,
+        fps_boost_until: None,
+        fps_boosted: false,
+        rates_changed: false,
+        step_mode: false,
+        dirty: true,
+        last_draw: None,
+        hooks: Vec::new(),
+        pinned: HashSet::new(),
+        retry: None,
+        last_loaded: None,
+        query: products::Query::default(),
+        held_key: None,
+        stale_shown: None,
     };
+    // the event log is the first hook, hooks registered after it see actions once they
+    // are recorded
+    app.register_hook(record_event);
+
+    if !config().OFFLINE && dispatch(&action_tx, Action::LoadProducts).is_break() {
+        tui.exit()?;
+        return Ok(());
+    }
+    // already offline, the load reports its own error and there is nothing to bound
+    if app.db_connected {
+        app.startup_deadline =
+            startup_remaining(startup).map(|remaining| Instant::now() + remaining);
+    }
+
+    let mut adaptive_rate = AdaptiveRate::default();
+    let mut focused = true;
 
     loop {
         let e = tui.next().await?;
-        match e {
-            tui::Event::Quit => action_tx.send(Action::Quit)?,
-            tui::Event::Tick => action_tx.send(Action::Tick)?,
-            tui::Event::Render => action_tx.send(Action::Render)?,
-            tui::Event::Key(_) => {
-                let action = get_action(&app, e);
-                action_tx.send(action.clone())?;
-            }
-            _ => {}
+        let action = match e {
+            tui::Event::Quit => Some(Action::Quit),
+            tui::Event::Tick if app.step_mode => None,
+            tui::Event::Tick => Some(Action::Tick),
+            tui::Event::Render => Some(Action::Render),
+            tui::Event::Resize(_, _) => Some(Action::Resize),
+            // with UNFOCUSED_RATE set, an unfocused terminal ticks and renders at most
+            // that often, and regaining focus restores whatever the adaptive rate wants
+            tui::Event::FocusLost => {
+                focused = false;
+                if let Some(rate) = config().UNFOCUSED_RATE {
+                    tracing::info!(rate, "terminal unfocused, slowing down");
+                    let (tick_rate, frame_rate) = rates(&app, focused);
+                    tui.set_rates(tick_rate, frame_rate);
+                }
+                None
+            }
+            tui::Event::FocusGained => {
+                focused = true;
+                if config().UNFOCUSED_RATE.is_some() {
+                    let (tick_rate, frame_rate) = rates(&app, focused);
+                    tui.set_rates(tick_rate, frame_rate);
+                }
+                Some(Action::Resize)
+            }
+            tui::Event::Key(_) | tui::Event::Mouse(_) => Some(get_action(&app, e)),
+            _ => None,
         };
+        if let Some(action) = action {
+            if dispatch(&action_tx, action).is_break() {
+                break;
+            }
+        }
 
+        let mut depth = 0;
         while let Ok(action) = action_rx.try_recv() {
-            // application update
+            depth += 1;
+            // application update, timed per action variant. Handled actions are logged at
+            // info so the default LOG_LEVEL carries them, the periodic ones only at debug.
+            match action {
+                Action::Tick | Action::Render | Action::None => {
+                    tracing::debug!(action = action.name(), "handling action")
+                }
+                _ => tracing::info!(action = action.name(), "handling action"),
+            }
+            let started = Instant::now();
+            let counter_before = app.counter;
             update(&mut app, action.clone());
-            // render only when we receive Action::Render
+            if app.counter != counter_before {
+                warn_near_limit(&mut app);
+            }
+            app.run_hooks(&action);
+            app.action_timings
+                .entry(action.name())
+                .or_default()
+                .record(started.elapsed());
+            if !matches!(action, Action::Render | Action::None) {
+                app.dirty = true;
+            }
+            // render only when we receive Action::Render, something changed, and the
+            // MAX_FPS frame window has passed. Render requests arriving within the same
+            // window collapse into the next draw, so an idle app stops redrawing entirely.
             if let Action::Render = action {
-                tui.draw(|f| {
-                    ui(f, &mut app);
-                })?;
+                // a stale indicator or relative timestamp counting up is a change too
+                if stale_age(&app) != app.stale_shown || relative_age(&app) != app.relative_shown {
+                    app.dirty = true;
+                }
+                let max_fps = if app.fps_boosted {
+                    app.live.MAX_FPS.max(config().FPS_BOOST)
+                } else {
+                    app.live.MAX_FPS
+                };
+                let frame_window = Duration::from_secs_f64(1.0 / max_fps);
+                let window_passed = app
+                    .last_draw
+                    .is_none_or(|last| last.elapsed() >= frame_window);
+                // without RENDER_DURING_REQUEST the frame with the request modal is the
+                // last one until the request finishes
+                let frozen = !config().RENDER_DURING_REQUEST
+                    && app.pending_request.is_some()
+                    && app.request_frame_drawn;
+                if app.dirty && window_passed && !frozen {
+                    let draw_started = Instant::now();
+                    tui.draw(|f| {
+                        ui(f, &mut app);
+                    })?;
+                    app.dirty = false;
+                    app.request_frame_drawn = app.pending_request.is_some();
+                    app.last_draw = Some(Instant::now());
+                    report_slow_frame(&mut app, draw_started.elapsed());
+                }
+            }
+        }
+
+        app.queue_depth = depth;
+        app.peak_queue_depth = app.peak_queue_depth.max(depth);
+
+        if config().ADAPTIVE_RATE {
+            if let Some(factor) = adaptive_rate.observe(depth) {
+                app.rate_slowed = adaptive_rate.slowed;
+                // while unfocused the low rate wins, focus gain applies the new factor
+                if focused || config().UNFOCUSED_RATE.is_none() {
+                    tracing::info!(factor, "adjusting tick and frame rate");
+                    let (tick_rate, frame_rate) = rates(&app, focused);
+                    tui.set_rates(tick_rate, frame_rate);
+                }
+            }
+        }
+
+        // a boost starting or running out restarts the tui at the matching rate
+        let boosted = app
+            .fps_boost_until
+            .is_some_and(|until| Instant::now() < until);
+        if boosted != app.fps_boosted {
+            app.fps_boosted = boosted;
+            if !boosted {
+                app.fps_boost_until = None;
+            }
+            let (tick_rate, frame_rate) = rates(&app, focused);
+            tracing::info!(
+                frame_rate,
+                "frame rate boost {}",
+                if boosted { "on" } else { "off" }
+            );
+            tui.set_rates(tick_rate, frame_rate);
+        }
+
+        // reloaded tick and frame rates
+        if app.rates_changed {
+            app.rates_changed = false;
+            let (tick_rate, frame_rate) = rates(&app, focused);
+            tracing::info!(tick_rate, frame_rate, "applying reloaded rates");
+            tui.set_rates(tick_rate, frame_rate);
+        }
+
+        // the initial load counts toward STARTUP_TIMEOUT_SECS too
+        if app
+            .startup_deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            app.startup_deadline = None;
+            match config().STARTUP_TIMEOUT_ACTION {
+                StartupTimeoutAction::Exit => startup_timed_out(&mut tui, "initial load"),
+                StartupTimeoutAction::Offline => {
+                    app.db_connected = false;
+                    update(
+                        &mut app,
+                        Action::Error(
+                            "initial load did not finish before the startup timeout".to_string(),
+                        ),
+                    );
+                }
             }
         }
 
-        // application exit
+        // application exit, lingering on a goodbye screen first if EXIT_DELAY_MS is set.
+        // Nothing reads events while it sleeps, so input is ignored.
         if app.should_quit {
+            if let (true, Ok(client)) = (app.persist_counter, &app.client) {
+                let save = products::save_counter(client, app.counter);
+                match tokio::time::timeout(PING_TIMEOUT, save).await {
+                    Ok(Ok(())) => tracing::info!(counter = app.counter, "counter saved"),
+                    Ok(Err(e)) => tracing::error!(error = %e, "could not save the counter"),
+                    Err(_) => tracing::error!("saving the counter timed out"),
+                }
+            }
+            // turned off this session, the saved counter goes so the next launch starts
+            // from INITIAL_COUNTER instead of a stale value
+            if let (false, true, Ok(client)) =
+                (app.persist_counter, config().PERSIST_COUNTER, &app.client)
+            {
+                let clear = products::clear_counter(client);
+                match tokio::time::timeout(PING_TIMEOUT, clear).await {
+                    Ok(Ok(())) => tracing::info!("saved counter cleared"),
+                    Ok(Err(e)) => tracing::error!(error = %e, "could not clear the saved counter"),
+                    Err(_) => tracing::error!("clearing the saved counter timed out"),
+                }
+            }
+            if config().EXIT_DELAY_MS > 0 {
+                tui.draw(|f| goodbye_screen(f, accent(&app)))?;
+                tokio::time::sleep(Duration::from_millis(config().EXIT_DELAY_MS)).await;
+            }
             break;
         }
     }
@@ -192,10 +3409,152 @@ async fn run() -> Result<()> {
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let result = run().await;
+    if std::env::args().any(|arg| arg == "--check") {
+        std::process::exit(check::run().await);
+    }
+
+    let startup = Instant::now();
+    logging::init()?;
+
+    let result = run(startup).await;
 
     result?;
 
     Ok(())
 }
 // ANCHOR_END: all
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dispatch_continues_while_the_loop_receives() {
+        for capacity in [None, Some(1)] {
+            let (action_tx, _action_rx) = channel::channel(capacity);
+            assert!(dispatch(&action_tx, Action::Tick).is_continue());
+            // a full bounded channel drops the action but keeps going
+            assert!(dispatch(&action_tx, Action::Tick).is_continue());
+        }
+    }
+
+    #[test]
+    fn dispatch_stops_once_the_receiver_is_dropped() {
+        for capacity in [None, Some(1)] {
+            let (action_tx, action_rx) = channel::channel(capacity);
+            drop(action_rx);
+            assert!(dispatch(&action_tx, Action::Quit).is_break());
+        }
+    }
+
+    // a request finishing after shutdown must still end its task instead of failing it
+    #[tokio::test]
+    async fn critical_tasks_exit_once_the_receiver_is_dropped() {
+        for capacity in [None, Some(1)] {
+            let (action_tx, action_rx) = channel::channel(capacity);
+            drop(action_rx);
+            let task = tokio::spawn(critical_task(action_tx, CancellationToken::new(), async {
+                Action::LoadProducts
+            }));
+            let finished = tokio::time::timeout(Duration::from_secs(1), task).await;
+            assert!(matches!(finished, Ok(Ok(()))));
+        }
+    }
+
+    #[tokio::test]
+    async fn cancelled_critical_tasks_exit_without_a_result() {
+        let (action_tx, mut action_rx) = channel::channel(None);
+        let token = CancellationToken::new();
+        token.cancel();
+        let task = tokio::spawn(critical_task(
+            action_tx,
+            token,
+            std::future::pending::<Action>(),
+        ));
+        let finished = tokio::time::timeout(Duration::from_secs(1), task).await;
+        assert!(matches!(finished, Ok(Ok(()))));
+        assert!(action_rx.try_recv().is_err());
+    }
+
+    fn format_without_seconds(format: &str) -> String {
+        let time = chrono::NaiveDate::from_ymd_opt(2024, 5, 6)
+            .and_then(|date| date.and_hms_opt(7, 8, 9))
+            .unwrap();
+        time.format_with_items(without_seconds(format).into_iter())
+            .to_string()
+    }
+
+    #[test]
+    fn seconds_and_their_separator_are_left_out() {
+        assert_eq!(
+            format_without_seconds("%Y-%m-%d %H:%M:%S"),
+            "2024-05-06 07:08"
+        );
+        assert_eq!(format_without_seconds("%T"), "07:08");
+        assert_eq!(format_without_seconds("%S s"), " s");
+    }
+
+    #[test]
+    fn escaped_seconds_stay_literal() {
+        assert_eq!(format_without_seconds("%Y 100%%S"), "2024 100%S");
+    }
+
+    #[test]
+    fn seller_ranges_parse_negative_bounds() {
+        assert_eq!(parse_seller_range("10-50"), Ok(Some((10, 50))));
+        assert_eq!(parse_seller_range("-5-10"), Ok(Some((-5, 10))));
+        assert_eq!(parse_seller_range("-10--5"), Ok(Some((-10, -5))));
+        assert_eq!(parse_seller_range(" -3 "), Ok(Some((-3, -3))));
+        assert_eq!(parse_seller_range(""), Ok(None));
+        assert!(parse_seller_range("10-5").is_err());
+        assert!(parse_seller_range("-").is_err());
+        assert!(parse_seller_range("é-5").is_err());
+    }
+
+    #[test]
+    fn saved_counter_wins_with_persist() {
+        assert_eq!(initial_counter(Some(42), true, 5), 42);
+    }
+
+    #[test]
+    fn initial_counter_without_a_saved_one() {
+        assert_eq!(initial_counter(None, true, 5), 5);
+        // a saved value is ignored unless PERSIST_COUNTER is set
+        assert_eq!(initial_counter(Some(42), false, 5), 5);
+    }
+
+    #[test]
+    fn zero_when_nothing_is_set() {
+        assert_eq!(initial_counter(None, false, 0), 0);
+        assert_eq!(initial_counter(None, true, 0), 0);
+    }
+
+    #[test]
+    fn bounded_add_wraps_up_past_max() {
+        assert_eq!(bounded_add(9, 1, Some(0), Some(9), true), 0);
+        assert_eq!(bounded_add(8, 5, Some(0), Some(9), true), 3);
+    }
+
+    #[test]
+    fn bounded_add_wraps_down_past_min() {
+        assert_eq!(bounded_add(0, -1, Some(0), Some(9), true), 9);
+        assert_eq!(bounded_add(-3, -2, Some(-3), Some(3), true), 2);
+    }
+
+    #[test]
+    fn bounded_add_clamps_without_wrap() {
+        assert_eq!(bounded_add(9, 5, Some(0), Some(9), false), 9);
+        assert_eq!(bounded_add(0, -5, Some(0), Some(9), false), 0);
+        // wrapping needs both bounds
+        assert_eq!(bounded_add(9, 5, None, Some(9), true), 9);
+        assert_eq!(bounded_add(i64::MAX, 1, None, None, false), i64::MAX);
+    }
+
+    #[test]
+    fn bounded_add_wraps_across_the_whole_i64_range() {
+        let (min, max) = (Some(i64::MIN), Some(i64::MAX));
+        assert_eq!(bounded_add(i64::MAX, 1, min, max, true), i64::MIN);
+        assert_eq!(bounded_add(i64::MIN, -1, min, max, true), i64::MAX);
+        assert_eq!(bounded_add(i64::MAX - 1, i64::MAX, min, max, true), -3);
+    }
+}
@@ -0,0 +1,56 @@
+// Panels drawn over the main view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overlay {
+    EventLog,
+    Timeline,
+    Debug,
+    ProductDetail,
+    About,
+    Config,
+    SellerHistogram,
+    Bookmarks,
+    Help,
+}
+
+// Open overlays, bottom first. They are drawn in this order, so the most recently
+// opened one is on top; it gets the keys and is the one Esc closes. Opening an overlay
+// that is already open raises it to the top.
+#[derive(Default)]
+pub struct Overlays {
+    stack: Vec<Overlay>,
+}
+
+impl Overlays {
+    pub fn open(&mut self, overlay: Overlay) {
+        self.close(overlay);
+        self.stack.push(overlay);
+    }
+
+    pub fn close(&mut self, overlay: Overlay) {
+        self.stack.retain(|open| *open != overlay);
+    }
+
+    pub fn toggle(&mut self, overlay: Overlay) {
+        if self.is_open(overlay) {
+            self.close(overlay);
+        } else {
+            self.open(overlay);
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<Overlay> {
+        self.stack.pop()
+    }
+
+    pub fn is_open(&self, overlay: Overlay) -> bool {
+        self.stack.contains(&overlay)
+    }
+
+    pub fn top(&self) -> Option<Overlay> {
+        self.stack.last().copied()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Overlay> + '_ {
+        self.stack.iter().copied()
+    }
+}
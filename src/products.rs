@@ -0,0 +1,387 @@
+use std::{future::Future, time::Duration};
+
+use bson::{doc, Document};
+use futures::TryStreamExt;
+use mongodb::{
+    error::ErrorKind,
+    options::{
+        ClientOptions, CollectionOptions, FindOptions, ReadPreference, ReadPreferenceOptions,
+        SelectionCriteria, UpdateOptions,
+    },
+    Client, Collection,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::configs::{config, ProductField, ReadMode, SortOrder};
+
+// How many suffixes a duplicate tries before giving up on a unique code
+const DUPLICATE_ATTEMPTS: u32 = 10;
+
+// Base delay between query retries, multiplied by the attempt number
+const RETRY_BACKOFF: Duration = Duration::from_millis(200);
+
+// Fields left out of the find projection fall back to their defaults
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Products {
+    pub name: String,
+    pub code: String,
+    pub seller_id: i32,
+}
+
+impl Products {
+    pub fn field(&self, field: ProductField) -> String {
+        match field {
+            ProductField::Name => self.name.clone(),
+            ProductField::Code => self.code.clone(),
+            ProductField::SellerId => self.seller_id.to_string(),
+        }
+    }
+}
+
+pub async fn connect() -> mongodb::error::Result<Client> {
+    let options = ClientOptions::parse(&config().MONGO_URI).await?;
+    Client::with_options(options)
+}
+
+pub async fn ping(client: &Client) -> mongodb::error::Result<()> {
+    client
+        .database(&config().MONGO_DATABASE)
+        .run_command(doc! { "ping": 1 }, None)
+        .await?;
+    Ok(())
+}
+
+// Runtime narrowing of the product query, on top of PRODUCT_FILTER
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    // inclusive seller_id bounds
+    pub seller_range: Option<(i32, i32)>,
+}
+
+impl Query {
+    fn filter(&self) -> Option<Document> {
+        let mut clauses: Vec<Document> = config().PRODUCT_FILTER.iter().cloned().collect();
+        if let Some((min, max)) = self.seller_range {
+            clauses.push(doc! { "seller_id": { "$gte": min, "$lte": max } });
+        }
+        match clauses.len() {
+            0 => None,
+            1 => clauses.pop(),
+            _ => Some(doc! { "$and": clauses }),
+        }
+    }
+}
+
+// `on_retry` is called with the retry number before each retry
+pub async fn load(
+    client: &Client,
+    query: &Query,
+    on_retry: impl Fn(u32),
+) -> mongodb::error::Result<Vec<Products>> {
+    with_retry(config().QUERY_RETRIES, on_retry, || find(client, query)).await
+}
+
+// The counter is kept as { _id: "counter", value } in COUNTER_COLLECTION
+fn counter_collection(client: &Client) -> Collection<Document> {
+    client
+        .database(&config().MONGO_DATABASE)
+        .collection(&config().COUNTER_COLLECTION)
+}
+
+pub async fn load_counter(client: &Client) -> mongodb::error::Result<Option<i64>> {
+    let document = counter_collection(client)
+        .find_one(doc! { "_id": "counter" }, None)
+        .await?;
+    Ok(document.and_then(|document| document.get_i64("value").ok()))
+}
+
+pub async fn save_counter(client: &Client, value: i64) -> mongodb::error::Result<()> {
+    let options = UpdateOptions::builder().upsert(true).build();
+    counter_collection(client)
+        .update_one(
+            doc! { "_id": "counter" },
+            doc! { "$set": { "value": value } },
+            options,
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn clear_counter(client: &Client) -> mongodb::error::Result<()> {
+    counter_collection(client)
+        .delete_one(doc! { "_id": "counter" }, None)
+        .await?;
+    Ok(())
+}
+
+pub async fn insert(client: &Client, product: &Products) -> mongodb::error::Result<()> {
+    collection(client).insert_one(product, None).await?;
+    Ok(())
+}
+
+pub async fn code_exists(client: &Client, code: &str) -> mongodb::error::Result<bool> {
+    let count = collection(client)
+        .count_documents(doc! { "code": code }, None)
+        .await?;
+    Ok(count > 0)
+}
+
+// Insert a copy of `product` under the first free "<code>-<n>", returning the code used.
+// The listed product may be projected down to DISPLAY_FIELDS, so the copy is made from
+// the full stored document.
+pub async fn duplicate(client: &Client, product: &Products) -> Result<String, String> {
+    let product = collection(client)
+        .find_one(doc! { "code": &product.code }, None)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("product {} no longer exists", product.code))?;
+    for n in 1..=DUPLICATE_ATTEMPTS {
+        let code = format!("{}-{n}", product.code);
+        if code_exists(client, &code)
+            .await
+            .map_err(|e| e.to_string())?
+        {
+            continue;
+        }
+        let copy = Products {
+            code: code.clone(),
+            ..product.clone()
+        };
+        insert(client, &copy).await.map_err(|e| e.to_string())?;
+        return Ok(code);
+    }
+    Err(format!(
+        "no free code for {} after {DUPLICATE_ATTEMPTS} attempts",
+        product.code
+    ))
+}
+
+// Checked before a product is written, collapsing stray whitespace in name and code
+pub fn validate(mut product: Products) -> Result<Products, String> {
+    product.name = normalize_whitespace(&product.name);
+    product.code = normalize_whitespace(&product.code);
+    if product.name.is_empty() || product.code.is_empty() {
+        return Err("name and code must not be empty".to_string());
+    }
+    if product.seller_id <= 0 {
+        return Err(format!(
+            "seller_id must be positive, got {}",
+            product.seller_id
+        ));
+    }
+    Ok(product)
+}
+
+fn normalize_whitespace(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn collection(client: &Client) -> Collection<Products> {
+    let options = CollectionOptions::builder()
+        .selection_criteria(SelectionCriteria::ReadPreference(read_preference()))
+        .build();
+    client
+        .database(&config().MONGO_DATABASE)
+        .collection_with_options::<Products>(&config().MONGO_COLLECTION, options)
+}
+
+fn read_preference() -> ReadPreference {
+    let options = ReadPreferenceOptions::default();
+    match config().READ_PREFERENCE {
+        ReadMode::Primary => ReadPreference::Primary,
+        ReadMode::PrimaryPreferred => ReadPreference::PrimaryPreferred { options },
+        ReadMode::Secondary => ReadPreference::Secondary { options },
+        ReadMode::SecondaryPreferred => ReadPreference::SecondaryPreferred { options },
+        ReadMode::Nearest => ReadPreference::Nearest { options },
+    }
+}
+
+async fn find(client: &Client, query: &Query) -> mongodb::error::Result<Vec<Products>> {
+    let collection = collection(client);
+    let options = FindOptions::builder()
+        .limit(config().MAX_PRODUCTS.map(|max| max as i64 + 1))
+        .projection(projection())
+        .sort(sort_document())
+        .build();
+    collection
+        .find(query.filter(), options)
+        .await?
+        .try_collect()
+        .await
+}
+
+fn sort_document() -> Option<Document> {
+    let field = config().SORT_FIELD?;
+    let direction = match config().SORT_ORDER {
+        SortOrder::Ascending => 1,
+        SortOrder::Descending => -1,
+    };
+    Some(doc! { field.label(): direction })
+}
+
+// Order `products` by SORT_FIELD in memory too, so the list keeps the configured order
+// whatever the server's collation does with strings
+pub fn sort(products: &mut [Products]) {
+    let Some(field) = config().SORT_FIELD else {
+        return;
+    };
+    products.sort_by(|a, b| {
+        let ordering = match field {
+            ProductField::SellerId => a.seller_id.cmp(&b.seller_id),
+            _ => a.field(field).cmp(&b.field(field)),
+        };
+        match config().SORT_ORDER {
+            SortOrder::Ascending => ordering,
+            SortOrder::Descending => ordering.reverse(),
+        }
+    });
+}
+
+// Only the displayed fields, plus the code that selection based actions look products
+// up by
+fn projection() -> Document {
+    let mut projection = doc! { "_id": 0, "code": 1 };
+    for field in &config().DISPLAY_FIELDS {
+        projection.insert(field.label(), 1);
+    }
+    // ROW_HIGHLIGHT needs its field even when it is not displayed
+    if let Some(rule) = &config().ROW_HIGHLIGHT {
+        projection.insert(rule.field.label(), 1);
+    }
+    projection
+}
+
+// Run `query` again up to `retries` times while it fails with a transient error
+async fn with_retry<T, F, Fut>(
+    retries: u32,
+    on_retry: impl Fn(u32),
+    mut query: F,
+) -> mongodb::error::Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = mongodb::error::Result<T>>,
+{
+    let mut attempt = 0;
+    loop {
+        match query().await {
+            Err(e) if attempt < retries && is_transient(&e) => {
+                attempt += 1;
+                on_retry(attempt);
+                tokio::time::sleep(RETRY_BACKOFF * attempt).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+// Bad credentials surface either while connecting or as an AuthenticationFailed (18)
+// command error on the first round trip
+pub fn is_auth_error(error: &mongodb::error::Error) -> bool {
+    match &*error.kind {
+        ErrorKind::Authentication { .. } => true,
+        ErrorKind::Command(command) => command.code == 18,
+        _ => false,
+    }
+}
+
+// Network blips and timeouts are worth retrying, auth and bad query errors are not
+fn is_transient(error: &mongodb::error::Error) -> bool {
+    matches!(
+        *error.kind,
+        ErrorKind::Io(_)
+            | ErrorKind::ConnectionPoolCleared { .. }
+            | ErrorKind::ServerSelection { .. }
+    ) || error.contains_label(mongodb::error::TRANSIENT_TRANSACTION_ERROR)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, io};
+
+    use super::*;
+
+    fn product(name: &str, code: &str, seller_id: i32) -> Products {
+        Products {
+            name: name.to_string(),
+            code: code.to_string(),
+            seller_id,
+        }
+    }
+
+    #[test]
+    fn validate_rejects_non_positive_seller_ids() {
+        assert!(validate(product("box", "B1", 0)).is_err());
+        assert!(validate(product("box", "B1", -4)).is_err());
+        assert!(validate(product("box", "B1", 1)).is_ok());
+    }
+
+    #[test]
+    fn validate_rejects_empty_name_or_code() {
+        assert!(validate(product("", "B1", 1)).is_err());
+        assert!(validate(product("   ", "B1", 1)).is_err());
+        assert!(validate(product("box", "", 1)).is_err());
+        assert!(validate(product("box", " \t ", 1)).is_err());
+    }
+
+    #[test]
+    fn validate_collapses_whitespace() {
+        let product = validate(product("  big \t  red   box ", " B1 ", 1)).unwrap();
+        assert_eq!(product.name, "big red box");
+        assert_eq!(product.code, "B1");
+    }
+
+    // what a find projected down to DISPLAY_FIELDS=code hands back
+    #[test]
+    fn partial_documents_deserialize() {
+        let product: Products = bson::from_document(doc! { "code": "B1" }).unwrap();
+        assert_eq!(product.code, "B1");
+        assert_eq!(product.name, "");
+        assert_eq!(product.seller_id, 0);
+    }
+
+    fn transient() -> mongodb::error::Error {
+        io::Error::new(io::ErrorKind::ConnectionReset, "connection reset").into()
+    }
+
+    #[tokio::test]
+    async fn with_retry_succeeds_after_transient_failures() {
+        let calls = RefCell::new(0);
+        let retries = RefCell::new(Vec::new());
+        let result = with_retry(
+            3,
+            |attempt| retries.borrow_mut().push(attempt),
+            || {
+                *calls.borrow_mut() += 1;
+                let call = *calls.borrow();
+                async move {
+                    if call <= 2 {
+                        Err(transient())
+                    } else {
+                        Ok("products")
+                    }
+                }
+            },
+        )
+        .await;
+        assert_eq!(result.unwrap(), "products");
+        assert_eq!(*calls.borrow(), 3);
+        assert_eq!(*retries.borrow(), vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn with_retry_fails_fast_on_other_errors() {
+        let calls = RefCell::new(0);
+        let result: mongodb::error::Result<()> = with_retry(
+            3,
+            |_| panic!("should not retry"),
+            || {
+                *calls.borrow_mut() += 1;
+                async { Err(mongodb::error::Error::custom("bad query")) }
+            },
+        )
+        .await;
+        assert!(result.is_err());
+        assert_eq!(*calls.borrow(), 1);
+    }
+}
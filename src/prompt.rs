@@ -0,0 +1,44 @@
+// What a single-line prompt asks for, decides how its input is parsed on submit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptKind {
+    NewProduct,
+    AddAmount,
+    GoToCode,
+    Bookmark,
+    SellerRange,
+    SimulateDelay,
+    Command,
+    AnimateTo,
+    Filter,
+}
+
+impl PromptKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            PromptKind::NewProduct => "new product: name, code, seller_id",
+            PromptKind::AddAmount => "add to counter (negative subtracts)",
+            PromptKind::GoToCode => "go to product code",
+            PromptKind::Bookmark => "bookmark label",
+            PromptKind::SellerRange => "seller_id range, e.g. 10-50 (empty shows all)",
+            PromptKind::SimulateDelay => "delay in milliseconds, then increment",
+            PromptKind::Command => "command",
+            PromptKind::AnimateTo => "count to",
+            PromptKind::Filter => "filter by name or code (Enter keeps, Esc clears)",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Prompt {
+    pub kind: PromptKind,
+    pub input: String,
+}
+
+impl Prompt {
+    pub fn new(kind: PromptKind) -> Self {
+        Self {
+            kind,
+            input: String::new(),
+        }
+    }
+}
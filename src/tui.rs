@@ -7,8 +7,10 @@ use color_eyre::eyre::Result;
 use crossterm::{
     cursor,
     event::{
-        DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
-        Event as CrosstermEvent, KeyEvent, KeyEventKind, MouseEvent,
+        DisableBracketedPaste, DisableFocusChange, DisableMouseCapture, EnableBracketedPaste,
+        EnableFocusChange, EnableMouseCapture, Event as CrosstermEvent, KeyEvent, KeyEventKind,
+        KeyboardEnhancementFlags, MouseEvent, PopKeyboardEnhancementFlags,
+        PushKeyboardEnhancementFlags,
     },
     terminal::{EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -47,6 +49,7 @@ pub struct Tui {
     pub tick_rate: f64,
     pub mouse: bool,
     pub paste: bool,
+    pub key_repeat: bool,
 }
 
 impl Tui {
@@ -59,6 +62,7 @@ impl Tui {
         let task = tokio::spawn(async {});
         let mouse = false;
         let paste = false;
+        let key_repeat = false;
         Ok(Self {
             terminal,
             task,
@@ -69,6 +73,7 @@ impl Tui {
             tick_rate,
             mouse,
             paste,
+            key_repeat,
         })
     }
 
@@ -82,7 +87,6 @@ impl Tui {
         self
     }
 
-    #[allow(dead_code)]
     pub fn mouse(mut self, mouse: bool) -> Self {
         self.mouse = mouse;
         self
@@ -94,6 +98,20 @@ impl Tui {
         self
     }
 
+    // Ask the terminal for key repeat and release events, where it supports them, and
+    // forward those along with presses
+    pub fn key_repeat(mut self, key_repeat: bool) -> Self {
+        self.key_repeat = key_repeat;
+        self
+    }
+
+    // Restart the event task with new rates, used to slow down and recover at runtime
+    pub fn set_rates(&mut self, tick_rate: f64, frame_rate: f64) {
+        self.tick_rate = tick_rate;
+        self.frame_rate = frame_rate;
+        self.start();
+    }
+
     pub fn start(&mut self) {
         let tick_delay = std::time::Duration::from_secs_f64(1.0 / self.tick_rate);
         let render_delay = std::time::Duration::from_secs_f64(1.0 / self.frame_rate);
@@ -101,6 +119,7 @@ impl Tui {
         self.cancellation_token = CancellationToken::new();
         let _cancellation_token = self.cancellation_token.clone();
         let _event_tx = self.event_tx.clone();
+        let key_repeat = self.key_repeat;
         self.task = tokio::spawn(async move {
             let mut reader = crossterm::event::EventStream::new();
             let mut tick_interval = tokio::time::interval(tick_delay);
@@ -119,7 +138,7 @@ impl Tui {
                       Some(Ok(evt)) => {
                         match evt {
                           CrosstermEvent::Key(key) => {
-                            if key.kind == KeyEventKind::Press {
+                            if key.kind == KeyEventKind::Press || key_repeat {
                               _event_tx.send(Event::Key(key)).unwrap();
                             }
                           },
@@ -176,13 +195,24 @@ impl Tui {
 
     pub fn enter(&mut self) -> Result<()> {
         crossterm::terminal::enable_raw_mode()?;
-        crossterm::execute!(std::io::stderr(), EnterAlternateScreen, cursor::Hide)?;
+        crossterm::execute!(
+            std::io::stderr(),
+            EnterAlternateScreen,
+            cursor::Hide,
+            EnableFocusChange
+        )?;
         if self.mouse {
             crossterm::execute!(std::io::stderr(), EnableMouseCapture)?;
         }
         if self.paste {
             crossterm::execute!(std::io::stderr(), EnableBracketedPaste)?;
         }
+        if self.key_repeat {
+            crossterm::execute!(
+                std::io::stderr(),
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+        }
         self.start();
         Ok(())
     }
@@ -194,10 +224,18 @@ impl Tui {
             if self.paste {
                 crossterm::execute!(std::io::stderr(), DisableBracketedPaste)?;
             }
+            if self.key_repeat {
+                crossterm::execute!(std::io::stderr(), PopKeyboardEnhancementFlags)?;
+            }
             if self.mouse {
                 crossterm::execute!(std::io::stderr(), DisableMouseCapture)?;
             }
-            crossterm::execute!(std::io::stderr(), LeaveAlternateScreen, cursor::Show)?;
+            crossterm::execute!(
+                std::io::stderr(),
+                DisableFocusChange,
+                LeaveAlternateScreen,
+                cursor::Show
+            )?;
             crossterm::terminal::disable_raw_mode()?;
         }
         Ok(())